@@ -0,0 +1,234 @@
+//! Generates the Candid request/response types and the `Service` methods of the CoreLedger
+//! canister from `candid/coreledger.did` into `$OUT_DIR/coreledger.rs`, which `src/agent.rs`
+//! includes below its hand-written parts.
+
+use candid::types::{FuncMode, Label};
+use candid_parser::{
+    types::{Binding, Dec, FuncType, IDLType, PrimType},
+    IDLProg,
+};
+use std::{env, fmt::Write, fs, path::Path};
+
+const DID_FILE: &str = "candid/coreledger.did";
+
+/// Types declared in the .did file that `src/agent.rs` provides itself, either as newtypes with a
+/// hand-written `CandidType` impl or because they need extra serde attributes. Their field names
+/// are checked at build time and their Candid types by a generated test.
+const HAND_WRITTEN_TYPES: &[&str] = &["U256", "UniqueAssetId", "Asset"];
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={}", DID_FILE);
+
+    // type-check the whole interface first so that mistakes are reported with Candid locations
+    candid_parser::pretty_check_file(Path::new(DID_FILE))
+        .unwrap_or_else(|e| panic!("invalid Candid interface {}: {}", DID_FILE, e));
+
+    let source = fs::read_to_string(DID_FILE).expect("cannot read the Candid interface");
+    let prog: IDLProg = source
+        .parse()
+        .unwrap_or_else(|e| panic!("cannot parse {}: {}", DID_FILE, e));
+
+    let mut code = format!(
+        "// Generated by build.rs from {}. Do not edit.\n\n",
+        DID_FILE
+    );
+    let mut hand_written = Vec::new();
+    for dec in &prog.decs {
+        match dec {
+            Dec::TypD(binding) if HAND_WRITTEN_TYPES.contains(&binding.id.as_str()) => {
+                hand_written.push(binding)
+            }
+            Dec::TypD(binding) => generate_type(&mut code, binding),
+            Dec::ImportType(file) | Dec::ImportServ(file) => {
+                panic!("{}: imports are not supported ({})", DID_FILE, file)
+            }
+        }
+    }
+
+    let methods = match &prog.actor {
+        Some(IDLType::ServT(methods)) => methods,
+        _ => panic!("{} must declare an inline service", DID_FILE),
+    };
+    code.push_str("impl Service {\n");
+    for method in methods {
+        let IDLType::FuncT(func) = &method.typ else {
+            panic!("{}: service member {} is not a method", DID_FILE, method.id)
+        };
+        generate_method(&mut code, &method.id, func);
+    }
+    code.push_str("}\n\n");
+    generate_interface_checks(&mut code, &hand_written);
+
+    let out_file = Path::new(&env::var("OUT_DIR").unwrap()).join("coreledger.rs");
+    fs::write(out_file, code).expect("cannot write the generated bindings");
+}
+
+fn generate_type(code: &mut String, binding: &Binding) {
+    let name = &binding.id;
+    match &binding.typ {
+        IDLType::RecordT(fields) => {
+            writeln!(
+                code,
+                "#[derive(CandidType, Deserialize)]\npub struct {} {{",
+                name
+            )
+            .unwrap();
+            for field in fields {
+                let Label::Named(field_name) = &field.label else {
+                    panic!("{}: record {} needs named fields", DID_FILE, name)
+                };
+                writeln!(code, "    pub {}: {},", field_name, rust_type(&field.typ)).unwrap();
+            }
+            code.push_str("}\n\n");
+        }
+        IDLType::VariantT(fields) => {
+            writeln!(
+                code,
+                "#[derive(CandidType, Deserialize)]\npub enum {} {{",
+                name
+            )
+            .unwrap();
+            for field in fields {
+                let Label::Named(variant_name) = &field.label else {
+                    panic!("{}: variant {} needs named cases", DID_FILE, name)
+                };
+                match &field.typ {
+                    IDLType::PrimT(PrimType::Null) => writeln!(code, "    {},", variant_name),
+                    typ => writeln!(code, "    {}({}),", variant_name, rust_type(typ)),
+                }
+                .unwrap();
+            }
+            code.push_str("}\n\n");
+        }
+        typ => {
+            writeln!(code, "pub type {} = {};\n", name, rust_type(typ)).unwrap();
+        }
+    }
+}
+
+/// Checks the hand-written types against their declarations. A function destructuring every
+/// record without `..` only compiles if the hand-written struct has exactly the declared fields;
+/// a test compares the Candid type of each hand-written type with a copy generated from the .did.
+fn generate_interface_checks(code: &mut String, bindings: &[&Binding]) {
+    let mut interface = String::new();
+    for binding in bindings {
+        if let IDLType::RecordT(fields) = &binding.typ {
+            let field_names = fields
+                .iter()
+                .map(|field| match &field.label {
+                    Label::Named(field_name) => field_name.clone(),
+                    _ => panic!("{}: record {} needs named fields", DID_FILE, binding.id),
+                })
+                .collect::<Vec<_>>();
+            writeln!(
+                code,
+                "#[allow(dead_code)]\nfn check_{}_fields(value: {}) {{\n    let {} {{ {} }} = value;\n}}\n",
+                binding.id.to_lowercase(),
+                binding.id,
+                binding.id,
+                field_names
+                    .iter()
+                    .map(|name| format!("{}: _", name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+            .unwrap();
+        }
+        generate_type(&mut interface, binding);
+    }
+
+    code.push_str("#[cfg(test)]\nmod interface {\n    use super::*;\n\n");
+    for line in interface.lines() {
+        if line.is_empty() {
+            code.push('\n');
+        } else {
+            writeln!(code, "    {}", line).unwrap();
+        }
+    }
+    code.push_str("    #[test]\n    fn hand_written_types_match_the_interface() {\n");
+    for binding in bindings {
+        writeln!(
+            code,
+            "        assert_eq!(<super::{0} as CandidType>::ty(), <{0} as CandidType>::ty(), \"{0}\");",
+            binding.id
+        )
+        .unwrap();
+    }
+    code.push_str("    }\n}\n");
+}
+
+/// The Candid parser drops argument names, so arguments are named `arg0`, `arg1`, ...
+fn generate_method(code: &mut String, name: &str, func: &FuncType) {
+    let arg_names = (0..func.args.len())
+        .map(|ix| format!("arg{}", ix))
+        .collect::<Vec<_>>();
+    let call = if func
+        .modes
+        .iter()
+        .any(|mode| matches!(mode, FuncMode::Query | FuncMode::CompositeQuery))
+    {
+        "query"
+    } else if func.modes.contains(&FuncMode::Oneway) {
+        panic!("{}: oneway method {} is not supported", DID_FILE, name)
+    } else {
+        "update"
+    };
+    let ret = match func.rets.as_slice() {
+        [] => "()".to_string(),
+        [ret] => rust_type(ret),
+        _ => panic!("{}: method {} returns more than one value", DID_FILE, name),
+    };
+
+    writeln!(code, "    pub async fn {}(\n        &self,", name).unwrap();
+    for (arg_name, arg) in arg_names.iter().zip(&func.args) {
+        writeln!(code, "        {}: &{},", arg_name, rust_type(arg)).unwrap();
+    }
+    writeln!(code, "    ) -> Result<{}, Box<dyn Error>> {{", ret).unwrap();
+    writeln!(code, "        let method_name = \"{}\";", name).unwrap();
+    writeln!(
+        code,
+        "        let args = Encode!({})?;",
+        arg_names.join(", ")
+    )
+    .unwrap();
+    writeln!(
+        code,
+        "        self.{}(method_name, args).await\n    }}\n",
+        call
+    )
+    .unwrap();
+}
+
+fn rust_type(typ: &IDLType) -> String {
+    match typ {
+        IDLType::PrimT(prim) => match prim {
+            PrimType::Nat => "Nat",
+            PrimType::Nat8 => "u8",
+            PrimType::Nat16 => "u16",
+            PrimType::Nat32 => "u32",
+            PrimType::Nat64 => "u64",
+            PrimType::Int => "candid::Int",
+            PrimType::Int8 => "i8",
+            PrimType::Int16 => "i16",
+            PrimType::Int32 => "i32",
+            PrimType::Int64 => "i64",
+            PrimType::Float32 => "f32",
+            PrimType::Float64 => "f64",
+            PrimType::Bool => "bool",
+            PrimType::Text => "String",
+            PrimType::Null => "()",
+            PrimType::Reserved => "candid::Reserved",
+            PrimType::Empty => "candid::Empty",
+        }
+        .to_string(),
+        IDLType::VarT(name) => name.clone(),
+        IDLType::PrincipalT => "Principal".to_string(),
+        IDLType::OptT(inner) => format!("Option<{}>", rust_type(inner)),
+        IDLType::VecT(inner) => format!("Vec<{}>", rust_type(inner)),
+        _ => panic!(
+            "{}: records, variants and references must be declared as named types",
+            DID_FILE
+        ),
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{
+    batch::{QueryRequest, QueryResponse},
+    document,
+    events::{self, Event, EventStream},
+    AmendmentId, AssetId, AssetUpdateCode, ContractId, EventType, Hash, LedgerId, Service,
+    TransactionEvent,
+};
+use std::{collections::HashMap, error::Error};
+
+pub struct AmendmentRecord {
+    pub amendment_id: AmendmentId,
+    /// Amended asset, `None` if no transaction ties the amendment to an asset event
+    pub asset_id: Option<AssetId>,
+    pub hash: Hash,
+    pub created_on: u64,
+}
+
+/// Amendments of a ledger in creation order, optionally only those of `asset_id`.
+///
+/// `AmendmentUpdate` events only carry the amendment id. The amended asset is taken from the
+/// `AmendmentCreationSuccess` event of the `AssetUpdate` stream that `get_tx` lists in the same
+/// transaction. Amendments without such a transaction are kept with an unknown asset, and left
+/// out when filtering by asset.
+pub async fn history(
+    service: &Service,
+    ledger_id: LedgerId,
+    ledger_contract_id: &ContractId,
+    asset_id: Option<&AssetId>,
+    max_in_flight: usize,
+) -> Result<Vec<AmendmentRecord>, Box<dyn Error>> {
+    let asset_events =
+        events::fetch_events(service, EventStream::AssetUpdate(ledger_id), max_in_flight).await?;
+    let mut amended_assets = HashMap::new();
+    for (event_ix, event) in asset_events {
+        let Event::AssetUpdate(event) = event else {
+            continue;
+        };
+        service.invalidate_asset(ledger_contract_id, &event.asset_id)?;
+        if matches!(event.event_id, AssetUpdateCode::AmendmentCreationSuccess) {
+            amended_assets.insert(event_ix, event.asset_id);
+        }
+    }
+
+    let mut amendments = Vec::new();
+    for (event_ix, event) in events::fetch_events(
+        service,
+        EventStream::AmendmentUpdate(ledger_id),
+        max_in_flight,
+    )
+    .await?
+    {
+        if let Event::AmendmentUpdate(event) = event {
+            service.invalidate_amendment(&event.amendment_id)?;
+            amendments.push((event_ix, event.amendment_id));
+        }
+    }
+
+    // amendment event -> asset creation event of the same transaction
+    let of_ledger = |e: &TransactionEvent| e.ledger_id == Some(ledger_id);
+    let transactions = events::fetch_transactions(
+        service,
+        |e| of_ledger(e) && matches!(e.event_type, EventType::AmendmentUpdate),
+        amendments.len(),
+        max_in_flight,
+    )
+    .await?;
+    let mut asset_of_amendment = HashMap::new();
+    for tx in &transactions {
+        let ledger_events = tx.events.iter().filter(|e| of_ledger(e));
+        let (amendment_events, asset_events): (Vec<_>, Vec<_>) = ledger_events
+            .filter(|e| {
+                matches!(
+                    e.event_type,
+                    EventType::AmendmentUpdate | EventType::AssetUpdate
+                )
+            })
+            .partition(|e| matches!(e.event_type, EventType::AmendmentUpdate));
+        let amended = asset_events
+            .iter()
+            .filter_map(|e| amended_assets.get(&e.event_ix))
+            .collect::<Vec<_>>();
+        // a transaction amending several assets cannot be paired up
+        if let ([amendment_event], [amended_asset_id]) = (&amendment_events[..], &amended[..]) {
+            asset_of_amendment.insert(amendment_event.event_ix, (*amended_asset_id).clone());
+        }
+    }
+
+    let amendments = amendments
+        .into_iter()
+        .map(|(event_ix, amendment_id)| (amendment_id, asset_of_amendment.remove(&event_ix)))
+        .filter(|(_, amended)| asset_id.is_none() || amended.as_ref() == asset_id)
+        .collect::<Vec<_>>();
+    let requests = amendments
+        .iter()
+        .map(|(amendment_id, _)| QueryRequest::Amendment(amendment_id.clone()))
+        .collect::<Vec<_>>();
+
+    let mut records = Vec::new();
+    for ((amendment_id, amended), result) in amendments
+        .into_iter()
+        .zip(service.batch_query(&requests, max_in_flight).await)
+    {
+        let QueryResponse::Amendment(amendment) = result? else {
+            continue;
+        };
+        let amendment = amendment.ok_or_else(|| format!("amendment {} not found", amendment_id))?;
+        records.push(AmendmentRecord {
+            amendment_id,
+            asset_id: amended,
+            hash: document::hash_from_bytes(&amendment.hash),
+            created_on: amendment.created_on,
+        });
+    }
+
+    Ok(records)
+}
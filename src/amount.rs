@@ -0,0 +1,361 @@
+use candid::{
+    types::{Serializer, Type, TypeInner},
+    CandidType, Nat,
+};
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::{error::Error, fmt, str::FromStr};
+
+/// Token amount checked to stay within 256 bits. On the wire it is a Candid `nat`, but values
+/// above 2^256 - 1 are rejected both when constructed locally and when decoded from the canister.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct U256(BigUint);
+
+#[derive(Debug)]
+pub enum AmountError {
+    Overflow,
+    Underflow,
+    DivisionByZero,
+    TooManyDecimals { decimals: u8 },
+    InvalidFormat(String),
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AmountError::Overflow => write!(f, "amount exceeds 2^256 - 1"),
+            AmountError::Underflow => write!(f, "amount would become negative"),
+            AmountError::DivisionByZero => write!(f, "division by zero"),
+            AmountError::TooManyDecimals { decimals } => {
+                write!(f, "amount has more than {} decimal places", decimals)
+            }
+            AmountError::InvalidFormat(text) => write!(f, "invalid amount: {}", text),
+        }
+    }
+}
+
+impl Error for AmountError {}
+
+impl U256 {
+    pub const BITS: u64 = 256;
+
+    pub fn zero() -> Self {
+        Self(BigUint::zero())
+    }
+
+    pub fn max_value() -> Self {
+        Self((BigUint::one() << Self::BITS) - BigUint::one())
+    }
+
+    pub fn new(value: BigUint) -> Result<Self, AmountError> {
+        if value.bits() > Self::BITS {
+            return Err(AmountError::Overflow);
+        }
+        Ok(Self(value))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn as_biguint(&self) -> &BigUint {
+        &self.0
+    }
+
+    pub fn to_nat(&self) -> Nat {
+        Nat(self.0.clone())
+    }
+
+    pub fn checked_add(&self, other: &U256) -> Result<Self, AmountError> {
+        Self::new(&self.0 + &other.0)
+    }
+
+    pub fn checked_sub(&self, other: &U256) -> Result<Self, AmountError> {
+        if other.0 > self.0 {
+            return Err(AmountError::Underflow);
+        }
+        Ok(Self(&self.0 - &other.0))
+    }
+
+    pub fn checked_mul(&self, other: &U256) -> Result<Self, AmountError> {
+        Self::new(&self.0 * &other.0)
+    }
+
+    pub fn checked_div(&self, other: &U256) -> Result<Self, AmountError> {
+        if other.is_zero() {
+            return Err(AmountError::DivisionByZero);
+        }
+        Ok(Self(&self.0 / &other.0))
+    }
+
+    /// Parses a display amount such as `1,250.5` into base units of an asset with `decimals`
+    /// display decimals. Commas are only accepted as thousands separators of the whole part, and
+    /// extra decimal places are rejected rather than silently truncated.
+    pub fn parse_units(text: &str, decimals: u8) -> Result<Self, AmountError> {
+        let invalid = || AmountError::InvalidFormat(text.to_string());
+
+        let (whole, fraction) = match text.trim().split_once('.') {
+            Some((whole, fraction)) => (whole, fraction),
+            None => (text.trim(), ""),
+        };
+        if whole.contains(',') && !is_grouped_by_thousands(whole) {
+            return Err(invalid());
+        }
+        let whole: String = whole.chars().filter(|c| *c != '_' && *c != ',').collect();
+        let fraction: String = fraction.chars().filter(|c| *c != '_').collect();
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(invalid());
+        }
+        if !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.len() > decimals as usize {
+            return Err(AmountError::TooManyDecimals { decimals });
+        }
+
+        let digits = format!("{}{:0<width$}", whole, fraction, width = decimals as usize);
+        let value = BigUint::parse_bytes(digits.as_bytes(), 10).unwrap_or_default();
+        Self::new(value)
+    }
+
+    /// Formats base units as a display amount with `decimals` display decimals, e.g. `1250.50`.
+    pub fn format_units(&self, decimals: u8) -> String {
+        let digits = self.0.to_string();
+        if decimals == 0 {
+            return digits;
+        }
+
+        let decimals = decimals as usize;
+        let digits = format!("{:0>width$}", digits, width = decimals + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - decimals);
+        format!("{}.{}", whole, fraction)
+    }
+}
+
+/// Whether `whole` is written as `1,234,567`: a leading group of one to three digits followed by
+/// groups of exactly three.
+fn is_grouped_by_thousands(whole: &str) -> bool {
+    let mut groups = whole.split(',');
+    let first = groups.next().unwrap_or_default();
+    (1..=3).contains(&first.len()) && groups.all(|group| group.len() == 3)
+}
+
+macro_rules! u256_from_primitive {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for U256 {
+                fn from(value: $t) -> Self {
+                    Self(BigUint::from(value))
+                }
+            }
+        )*
+    };
+}
+
+u256_from_primitive!(u8, u16, u32, u64, u128);
+
+impl TryFrom<Nat> for U256 {
+    type Error = AmountError;
+
+    fn try_from(value: Nat) -> Result<Self, Self::Error> {
+        Self::new(value.0)
+    }
+}
+
+impl From<U256> for Nat {
+    fn from(value: U256) -> Self {
+        Nat(value.0)
+    }
+}
+
+impl FromStr for U256 {
+    type Err = AmountError;
+
+    /// Parses an amount in base units.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse_units(text, 0)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Debug for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "U256({})", self.0)
+    }
+}
+
+impl CandidType for U256 {
+    fn _ty() -> Type {
+        TypeInner::Nat.into()
+    }
+
+    fn idl_serialize<S>(&self, serializer: S) -> Result<(), S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_nat(&self.to_nat())
+    }
+}
+
+impl Serialize for U256 {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.0.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for U256 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            let text = String::deserialize(deserializer)?;
+            text.parse().map_err(de::Error::custom)
+        } else {
+            // the Candid deserializer is not human readable and yields a `nat`
+            let value = Nat::deserialize(deserializer)?;
+            Self::try_from(value).map_err(de::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::{Decode, Encode};
+
+    #[test]
+    fn parse_units_scales_by_decimals() {
+        assert_eq!(
+            U256::parse_units("1,250.5", 2).unwrap(),
+            U256::from(125_050u32)
+        );
+        assert_eq!(U256::parse_units("0.01", 2).unwrap(), U256::from(1u8));
+        assert_eq!(U256::parse_units(".5", 1).unwrap(), U256::from(5u8));
+        assert_eq!(U256::parse_units("7.", 0).unwrap(), U256::from(7u8));
+        assert_eq!(
+            U256::parse_units(" 1_000 ", 0).unwrap(),
+            U256::from(1000u32)
+        );
+        // trailing zeros beyond the display decimals carry no precision
+        assert_eq!(U256::parse_units("3.1400", 2).unwrap(), U256::from(314u32));
+    }
+
+    #[test]
+    fn parse_units_rejects_too_many_decimals() {
+        assert!(matches!(
+            U256::parse_units("1.234", 2),
+            Err(AmountError::TooManyDecimals { decimals: 2 })
+        ));
+        assert!(matches!(
+            U256::parse_units("0.5", 0),
+            Err(AmountError::TooManyDecimals { decimals: 0 })
+        ));
+    }
+
+    #[test]
+    fn parse_units_accepts_only_thousands_separators() {
+        assert_eq!(
+            U256::parse_units("1,234,567", 0).unwrap(),
+            U256::from(1_234_567u32)
+        );
+        for text in [
+            "1,2,3", ",123", "1234,567", "1,23", "1,2345", "1.000,5", "1,",
+        ] {
+            assert!(
+                matches!(
+                    U256::parse_units(text, 3),
+                    Err(AmountError::InvalidFormat(_))
+                ),
+                "{text} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_units_rejects_malformed_amounts() {
+        for text in ["", ".", "-1", "1e3", "1.2.3", "abc", "0x10"] {
+            assert!(
+                U256::parse_units(text, 3).is_err(),
+                "{text} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_units_checks_the_upper_bound() {
+        let max = U256::max_value();
+        assert_eq!(U256::parse_units(&max.to_string(), 0).unwrap(), max);
+
+        let above = (BigUint::one() << U256::BITS).to_string();
+        assert!(matches!(
+            U256::parse_units(&above, 0),
+            Err(AmountError::Overflow)
+        ));
+        // in range as display units, out of range as base units
+        assert!(matches!(
+            U256::parse_units(&max.to_string(), 1),
+            Err(AmountError::Overflow)
+        ));
+    }
+
+    #[test]
+    fn format_units_round_trips() {
+        assert_eq!(U256::from(125_050u32).format_units(2), "1250.50");
+        assert_eq!(U256::from(5u8).format_units(3), "0.005");
+        assert_eq!(U256::zero().format_units(2), "0.00");
+        assert_eq!(U256::from(42u8).format_units(0), "42");
+
+        for (value, decimals) in [(0u64, 0u8), (1, 18), (123_456_789, 4), (u64::MAX, 9)] {
+            let amount = U256::from(value);
+            let text = amount.format_units(decimals);
+            assert_eq!(U256::parse_units(&text, decimals).unwrap(), amount);
+        }
+    }
+
+    #[test]
+    fn checked_arithmetic_stays_in_range() {
+        let max = U256::max_value();
+        assert!(matches!(
+            max.checked_add(&U256::from(1u8)),
+            Err(AmountError::Overflow)
+        ));
+        assert!(matches!(
+            U256::from(1u8).checked_sub(&U256::from(2u8)),
+            Err(AmountError::Underflow)
+        ));
+        assert!(matches!(
+            max.checked_mul(&U256::from(2u8)),
+            Err(AmountError::Overflow)
+        ));
+        assert!(matches!(
+            max.checked_div(&U256::zero()),
+            Err(AmountError::DivisionByZero)
+        ));
+    }
+
+    #[test]
+    fn decoding_rejects_values_above_256_bits() {
+        let too_large = Nat(BigUint::one() << U256::BITS);
+        let bytes = Encode!(&too_large).unwrap();
+        assert!(Decode!(&bytes, U256).is_err());
+
+        let bytes = Encode!(&U256::max_value()).unwrap();
+        assert_eq!(Decode!(&bytes, U256).unwrap(), U256::max_value());
+    }
+}
@@ -0,0 +1,69 @@
+use std::cell::RefCell;
+
+/// Outcome of a single assertion step of a scenario.
+pub struct AssertionResult {
+    pub description: String,
+    pub failure: Option<String>,
+}
+
+/// Collects assertion results while a scenario runs and renders the run summary.
+#[derive(Default)]
+pub struct Assertions {
+    results: RefCell<Vec<AssertionResult>>,
+}
+
+impl Assertions {
+    pub fn record(&self, description: String, outcome: Result<(), String>) {
+        match &outcome {
+            Ok(()) => println!("[pass] {}", description),
+            Err(reason) => println!("[FAIL] {}: {}", description, reason),
+        }
+
+        self.results.borrow_mut().push(AssertionResult {
+            description,
+            failure: outcome.err(),
+        });
+    }
+
+    pub fn passed(&self) -> usize {
+        self.results
+            .borrow()
+            .iter()
+            .filter(|r| r.failure.is_none())
+            .count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.results
+            .borrow()
+            .iter()
+            .filter(|r| r.failure.is_some())
+            .count()
+    }
+
+    pub fn print_summary(&self) {
+        use prettytable::{Cell, Row, Table};
+
+        let mut table = Table::new();
+        table.add_row(Row::new(vec![
+            Cell::new("Result"),
+            Cell::new("Assertion"),
+            Cell::new("Details"),
+        ]));
+        for result in self.results.borrow().iter() {
+            let (status, details) = match &result.failure {
+                None => ("pass", ""),
+                Some(reason) => ("FAIL", reason.as_str()),
+            };
+            table.add_row(Row::new(vec![
+                Cell::new(status),
+                Cell::new(&result.description),
+                Cell::new(details),
+            ]));
+        }
+
+        println!("\nRun summary:");
+        table.printstd();
+        println!("{} passed, {} failed", self.passed(), self.failed());
+    }
+}
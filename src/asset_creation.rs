@@ -0,0 +1,243 @@
+use crate::{
+    registry::AssetRegistry, Asset, AssetId, ContractId, Hash, LedgerId, Service, UniqueAssetId,
+};
+use candid::Nat;
+use num_bigint::BigUint;
+use rand::Rng;
+use std::error::Error;
+use tracing::warn;
+
+/// Number of random asset ids tried before giving up on finding an unused one.
+pub const MAX_ASSET_ID_ATTEMPTS: usize = 5;
+
+pub struct CreateAssetRequest {
+    pub asset_id: AssetId,
+    pub asset_hash: Hash,
+    pub asset_bitwise: bool,
+    pub asset_controller: Option<ContractId>,
+}
+
+impl CreateAssetRequest {
+    pub fn new(asset_id: AssetId) -> Self {
+        Self {
+            asset_id,
+            asset_hash: Nat::from(0x42_u128),
+            asset_bitwise: false,
+            asset_controller: None,
+        }
+    }
+}
+
+/// Random 80-bit asset id, the size that fits a unique asset id.
+pub fn random_asset_id(rng: &mut impl Rng) -> AssetId {
+    let asset_id_bytes: [u8; 10] = rng.gen();
+    Nat(BigUint::from_bytes_be(&asset_id_bytes))
+}
+
+/// Creation state of an asset id on the ledger.
+#[derive(Clone, Copy, PartialEq)]
+pub enum AssetState {
+    Unused,
+    /// Reserved by `led_base_create_asset`, not activated yet
+    Reserved,
+    /// Activated; only activation sets the asset hash
+    Active,
+}
+
+/// Ledger calls that create an asset, in order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CreationStep {
+    /// `led_base_create_asset`
+    Reserve,
+    /// `led_base_activate_asset`
+    Activate,
+}
+
+impl AssetState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AssetState::Unused => "unused",
+            AssetState::Reserved => "reserved",
+            AssetState::Active => "active",
+        }
+    }
+
+    /// State of an asset id given what `led_base_get_asset` returned for it.
+    pub fn of(asset: Option<&Asset>) -> Self {
+        match asset {
+            None => AssetState::Unused,
+            Some(asset) if asset.hash.is_none() => AssetState::Reserved,
+            Some(_) => AssetState::Active,
+        }
+    }
+
+    /// Steps still needed to finish a creation that stopped in this state.
+    pub fn remaining_steps(self) -> &'static [CreationStep] {
+        match self {
+            AssetState::Unused => &[CreationStep::Reserve, CreationStep::Activate],
+            AssetState::Reserved => &[CreationStep::Activate],
+            AssetState::Active => &[],
+        }
+    }
+}
+
+pub async fn asset_state(
+    service: &Service,
+    contract_id: &ContractId,
+    asset_id: &AssetId,
+) -> Result<AssetState, Box<dyn Error>> {
+    let asset = service.led_base_get_asset(contract_id, asset_id).await?;
+    Ok(AssetState::of(asset.as_ref()))
+}
+
+/// Draws asset ids from `next_asset_id` until one is neither known to the ledger nor to the
+/// registry, so that a random id never silently takes over an existing asset.
+pub async fn unused_asset_id(
+    service: &Service,
+    registry: &AssetRegistry,
+    contract_id: &ContractId,
+    ledger_id: LedgerId,
+    next_asset_id: impl FnMut() -> AssetId,
+) -> Result<AssetId, Box<dyn Error>> {
+    find_unused_asset_id(registry, ledger_id, next_asset_id, async |asset_id| {
+        asset_state(service, contract_id, asset_id).await
+    })
+    .await
+}
+
+/// `unused_asset_id` with the ledger state of an asset id looked up by `state_of`.
+async fn find_unused_asset_id(
+    registry: &AssetRegistry,
+    ledger_id: LedgerId,
+    mut next_asset_id: impl FnMut() -> AssetId,
+    mut state_of: impl AsyncFnMut(&AssetId) -> Result<AssetState, Box<dyn Error>>,
+) -> Result<AssetId, Box<dyn Error>> {
+    for _ in 0..MAX_ASSET_ID_ATTEMPTS {
+        let asset_id = next_asset_id();
+        let unique_asset_id = UniqueAssetId::compose(&asset_id, ledger_id)?;
+        let known = registry.by_unique_asset_id(&unique_asset_id).is_some()
+            || registry.reservation(&unique_asset_id.to_hex()).is_some();
+        if !known && state_of(&asset_id).await? == AssetState::Unused {
+            return Ok(asset_id);
+        }
+        warn!(%unique_asset_id, "asset id collision, drawing another one");
+    }
+
+    Err(format!(
+        "no unused asset id found in {} attempts",
+        MAX_ASSET_ID_ATTEMPTS
+    )
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::ReservedAsset;
+    use candid::Principal;
+    use std::{cell::Cell, collections::HashMap, rc::Rc};
+
+    const LEDGER_ID: LedgerId = 1;
+
+    /// Generator handing out `ids` in order, counting the draws.
+    fn ids(ids: &[u32]) -> (impl FnMut() -> AssetId + '_, Rc<Cell<usize>>) {
+        let drawn = Rc::new(Cell::new(0));
+        let counter = drawn.clone();
+        let next = move || {
+            let asset_id = Nat::from(ids[counter.get()]);
+            counter.set(counter.get() + 1);
+            asset_id
+        };
+        (next, drawn)
+    }
+
+    /// Ledger state lookup answering from `states`, unused for any other id.
+    fn ledger(
+        states: HashMap<u32, AssetState>,
+    ) -> impl AsyncFnMut(&AssetId) -> Result<AssetState, Box<dyn Error>> {
+        async move |asset_id: &AssetId| {
+            let asset_id = u32::try_from(asset_id.0.clone()).unwrap();
+            Ok(states.get(&asset_id).copied().unwrap_or(AssetState::Unused))
+        }
+    }
+
+    #[tokio::test]
+    async fn ids_used_on_the_ledger_are_skipped() {
+        let registry = AssetRegistry::ephemeral();
+        let (next, drawn) = ids(&[1, 2, 3]);
+        let states = HashMap::from([(1, AssetState::Active), (2, AssetState::Reserved)]);
+
+        let asset_id = find_unused_asset_id(&registry, LEDGER_ID, next, ledger(states))
+            .await
+            .unwrap();
+        assert_eq!(asset_id, Nat::from(3u8));
+        assert_eq!(drawn.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn ids_known_to_the_registry_are_skipped() {
+        let registry = AssetRegistry::ephemeral();
+        registry
+            .register("RE", 2, &Nat::from(1u8), LEDGER_ID)
+            .unwrap();
+        registry.add_reservation(ReservedAsset {
+            symbol: "USD".to_string(),
+            decimals: 2,
+            unique_asset_id: UniqueAssetId::compose(&Nat::from(2u8), LEDGER_ID).unwrap(),
+            issuer: "alice".to_string(),
+            reserved_at: "2026-01-01T00:00:00+00:00".to_string(),
+        });
+        let (next, _) = ids(&[1, 2, 3]);
+
+        let asset_id = find_unused_asset_id(&registry, LEDGER_ID, next, ledger(HashMap::new()))
+            .await
+            .unwrap();
+        assert_eq!(asset_id, Nat::from(3u8));
+    }
+
+    #[tokio::test]
+    async fn drawing_stops_after_the_last_attempt() {
+        let registry = AssetRegistry::ephemeral();
+        let taken = [1, 2, 3, 4, 5, 6];
+        let (next, drawn) = ids(&taken);
+        let states = taken.iter().map(|id| (*id, AssetState::Active)).collect();
+
+        let error = find_unused_asset_id(&registry, LEDGER_ID, next, ledger(states))
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "no unused asset id found in 5 attempts");
+        assert_eq!(drawn.get(), MAX_ASSET_ID_ATTEMPTS);
+    }
+
+    fn asset(hash: Option<[u8; 32]>) -> Asset {
+        Asset {
+            bitwise: false,
+            hash,
+            created_on: None,
+            issuer: Principal::anonymous(),
+        }
+    }
+
+    #[test]
+    fn only_activation_sets_the_hash() {
+        assert!(AssetState::of(None) == AssetState::Unused);
+        assert!(AssetState::of(Some(&asset(None))) == AssetState::Reserved);
+        assert!(AssetState::of(Some(&asset(Some([7; 32])))) == AssetState::Active);
+    }
+
+    #[test]
+    fn interrupted_creations_resume_where_they_stopped() {
+        // the reservation never reached the ledger
+        assert_eq!(
+            AssetState::Unused.remaining_steps(),
+            [CreationStep::Reserve, CreationStep::Activate]
+        );
+        // reserved, but activation did not happen
+        assert_eq!(
+            AssetState::Reserved.remaining_steps(),
+            [CreationStep::Activate]
+        );
+        // activated, only the registry was not updated
+        assert!(AssetState::Active.remaining_steps().is_empty());
+    }
+}
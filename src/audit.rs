@@ -0,0 +1,326 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    cell::RefCell,
+    error::Error,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// One update call as recorded in the audit log. `hash` covers all other fields including
+/// `prev_hash`, which chains every entry to its predecessor.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub seq: u64,
+    pub timestamp: String,
+    pub method: String,
+    pub caller: String,
+    /// Candid-encoded arguments, hex
+    pub args: String,
+    pub outcome: String,
+    pub tx_id: Option<String>,
+    pub error: Option<String>,
+    pub prev_hash: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AuditEntry {
+    #[serde(flatten)]
+    pub record: AuditRecord,
+    pub hash: String,
+}
+
+impl AuditRecord {
+    fn hash(&self) -> Result<String, Box<dyn Error>> {
+        let json = serde_json::to_string(self)?;
+        Ok(hex::encode(Sha256::digest(json.as_bytes())))
+    }
+}
+
+/// A non-empty line of the audit log with its 1-based line number; `entry` holds the parse error
+/// of a malformed line.
+pub struct AuditLine {
+    pub line_number: usize,
+    pub entry: Result<AuditEntry, String>,
+}
+
+/// Outcome of an update call to be appended to the audit log.
+pub struct AuditEvent<'a> {
+    pub method: &'a str,
+    pub caller: &'a str,
+    pub args: &'a [u8],
+    pub result: Result<Option<String>, String>,
+}
+
+/// Append-only, hash-chained JSONL log of update calls.
+///
+/// The chain detects modified, removed or reordered entries, but not entries cut off at the end
+/// of the file: a truncated log is a valid shorter chain. `audit verify` prints the head of the
+/// chain so that it can be compared with a copy kept elsewhere.
+pub struct AuditLog {
+    file_path: PathBuf,
+    // sequence number and hash of the last entry
+    head: RefCell<(u64, String)>,
+}
+
+impl AuditLog {
+    /// Opens the log for appending; a missing file starts a new chain. Malformed lines do not
+    /// prevent appending, new entries continue from the last well-formed one and `audit verify`
+    /// reports the damage.
+    pub fn open<P: AsRef<Path>>(file_path: P) -> Result<Self, Box<dyn Error>> {
+        let file_path = file_path.as_ref().to_path_buf();
+        let lines = Self::read(&file_path)?;
+        let malformed = lines.iter().filter(|line| line.entry.is_err()).count();
+        if malformed > 0 {
+            warn!(
+                file = %file_path.display(),
+                malformed,
+                "audit log has malformed lines, run `audit verify`"
+            );
+        }
+        let last_hash = lines
+            .iter()
+            .rev()
+            .find_map(|line| line.entry.as_ref().ok())
+            .map_or_else(|| GENESIS_HASH.to_string(), |entry| entry.hash.clone());
+
+        Ok(Self {
+            file_path,
+            head: RefCell::new((lines.len() as u64, last_hash)),
+        })
+    }
+
+    /// Reads all non-empty lines; only a file that cannot be read is an error.
+    pub fn read<P: AsRef<Path>>(file_path: P) -> Result<Vec<AuditLine>, Box<dyn Error>> {
+        let content = match fs::read_to_string(file_path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(ix, line)| AuditLine {
+                line_number: ix + 1,
+                entry: serde_json::from_str(line).map_err(|e| e.to_string()),
+            })
+            .collect())
+    }
+
+    pub fn append(&self, event: AuditEvent) -> Result<(), Box<dyn Error>> {
+        let mut head = self.head.borrow_mut();
+        let (tx_id, error) = match event.result {
+            Ok(tx_id) => (tx_id, None),
+            Err(error) => (None, Some(error)),
+        };
+        let record = AuditRecord {
+            seq: head.0,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            method: event.method.to_string(),
+            caller: event.caller.to_string(),
+            args: hex::encode(event.args),
+            outcome: if error.is_none() { "ok" } else { "error" }.to_string(),
+            tx_id,
+            error,
+            prev_hash: head.1.clone(),
+        };
+        let entry = AuditEntry {
+            hash: record.hash()?,
+            record,
+        };
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.file_path)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        file.flush()?;
+
+        *head = (entry.record.seq + 1, entry.hash);
+        Ok(())
+    }
+}
+
+/// Hash of the last well-formed entry, the head of the chain.
+pub fn head_hash(lines: &[AuditLine]) -> Option<&str> {
+    lines
+        .iter()
+        .rev()
+        .find_map(|line| line.entry.as_ref().ok())
+        .map(|entry| entry.hash.as_str())
+}
+
+/// Checks that every line is an entry and checks sequence numbers, hash links and entry hashes.
+/// Returns one problem description per broken line, keyed by its line number. The entry after a
+/// malformed line is not checked against its predecessor, whose hash is unknown.
+pub fn verify_chain(lines: &[AuditLine]) -> Vec<(usize, String)> {
+    let mut problems = Vec::new();
+    let mut prev_hash = Some(GENESIS_HASH.to_string());
+
+    for (ix, line) in lines.iter().enumerate() {
+        let line_number = line.line_number;
+        let entry = match &line.entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                problems.push((line_number, format!("malformed entry: {}", e)));
+                prev_hash = None;
+                continue;
+            }
+        };
+        if entry.record.seq != ix as u64 {
+            problems.push((
+                line_number,
+                format!("sequence number {} out of order", entry.record.seq),
+            ));
+        }
+        if prev_hash
+            .as_ref()
+            .is_some_and(|h| *h != entry.record.prev_hash)
+        {
+            problems.push((
+                line_number,
+                "link to the previous entry is broken".to_string(),
+            ));
+        }
+        match entry.record.hash() {
+            Ok(hash) if hash == entry.hash => {}
+            Ok(_) => problems.push((line_number, "entry was modified".to_string())),
+            Err(e) => problems.push((line_number, e.to_string())),
+        }
+        prev_hash = Some(entry.hash.clone());
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_log(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("audit-{}-{}.jsonl", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn write_log(name: &str, calls: usize) -> PathBuf {
+        let path = temp_log(name);
+        let log = AuditLog::open(&path).unwrap();
+        for ix in 0..calls {
+            log.append(AuditEvent {
+                method: "int_transfer_tokens",
+                caller: "2vxsx-fae",
+                args: &[ix as u8],
+                result: if ix % 2 == 0 {
+                    Ok(Some(ix.to_string()))
+                } else {
+                    Err("rejected".to_string())
+                },
+            })
+            .unwrap();
+        }
+        path
+    }
+
+    fn rewrite_lines(path: &Path, edit: impl FnOnce(&mut Vec<String>)) {
+        let mut lines = fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        edit(&mut lines);
+        fs::write(path, lines.join("\n") + "\n").unwrap();
+    }
+
+    fn problems(path: &Path) -> Vec<(usize, String)> {
+        verify_chain(&AuditLog::read(path).unwrap())
+    }
+
+    #[test]
+    fn intact_chain_verifies() {
+        let path = write_log("intact", 4);
+        let lines = AuditLog::read(&path).unwrap();
+        assert_eq!(lines.len(), 4);
+        assert!(verify_chain(&lines).is_empty());
+        assert_eq!(
+            head_hash(&lines),
+            lines[3].entry.as_ref().ok().map(|e| e.hash.as_str())
+        );
+        assert!(AuditLog::read(temp_log("missing")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn modified_entry_is_detected() {
+        let path = write_log("modified", 3);
+        rewrite_lines(&path, |lines| {
+            lines[1] = lines[1].replace("rejected", "accepted");
+        });
+        assert_eq!(problems(&path), vec![(2, "entry was modified".to_string())]);
+    }
+
+    #[test]
+    fn removed_entry_breaks_the_chain() {
+        let path = write_log("removed", 3);
+        rewrite_lines(&path, |lines| {
+            lines.remove(1);
+        });
+        assert_eq!(
+            problems(&path),
+            vec![
+                (2, "sequence number 2 out of order".to_string()),
+                (2, "link to the previous entry is broken".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_tail_is_not_detected() {
+        let path = write_log("truncated", 3);
+        rewrite_lines(&path, |lines| {
+            lines.pop();
+        });
+        assert!(problems(&path).is_empty());
+    }
+
+    #[test]
+    fn malformed_line_is_reported_with_its_line_number() {
+        let path = write_log("malformed", 3);
+        rewrite_lines(&path, |lines| {
+            lines[1] = "{not json".to_string();
+            lines.insert(0, String::new());
+        });
+        let problems = problems(&path);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].0, 3);
+        assert!(problems[0].1.starts_with("malformed entry"));
+    }
+
+    #[test]
+    fn open_appends_after_a_malformed_line() {
+        let path = write_log("reopen", 2);
+        rewrite_lines(&path, |lines| lines.push("garbage".to_string()));
+        let log = AuditLog::open(&path).unwrap();
+        log.append(AuditEvent {
+            method: "int_issue_tokens",
+            caller: "2vxsx-fae",
+            args: &[],
+            result: Ok(None),
+        })
+        .unwrap();
+
+        let lines = AuditLog::read(&path).unwrap();
+        assert_eq!(lines.len(), 4);
+        let appended = lines[3].entry.as_ref().ok().unwrap();
+        assert_eq!(appended.record.seq, 3);
+        // the garbage line is the only problem, the new entry is not checked against it
+        assert_eq!(problems(&path).len(), 1);
+        assert_eq!(problems(&path)[0].0, 3);
+    }
+}
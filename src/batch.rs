@@ -0,0 +1,173 @@
+#![allow(dead_code)]
+
+use crate::events::{Event, EventStream};
+use crate::{
+    Amendment, AmendmentId, Asset, AssetId, ContractId, Service, Supply, SupplyId, Transaction,
+    TxId, UniqueAssetId, U256,
+};
+use candid::Principal;
+use futures::stream::{self, StreamExt};
+use std::{error::Error, future::Future};
+
+/// Number of queries `Service::batch_query` keeps in flight unless told otherwise.
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 8;
+
+/// Runs `run` on every item with at most `max_in_flight` futures outstanding at a time, and
+/// returns their outputs in the order of the items.
+async fn run_batched<'a, T, F, Fut>(
+    items: &'a [T],
+    max_in_flight: usize,
+    run: F,
+) -> Vec<Fut::Output>
+where
+    F: FnMut(&'a T) -> Fut,
+    Fut: Future,
+{
+    stream::iter(items)
+        .map(run)
+        .buffered(max_in_flight.max(1))
+        .collect()
+        .await
+}
+
+/// A single read of a batch.
+pub enum QueryRequest {
+    Balance {
+        unique_asset_id: UniqueAssetId,
+        holder: Principal,
+    },
+    Supply(SupplyId),
+    Asset {
+        contract_id: ContractId,
+        asset_id: AssetId,
+    },
+    Event {
+        stream: EventStream,
+        event_ix: u64,
+    },
+    Transaction(TxId),
+    Amendment(AmendmentId),
+}
+
+/// Result of a `QueryRequest`, of the variant matching the request.
+pub enum QueryResponse {
+    Balance(U256),
+    Supply(Option<Supply>),
+    Asset(Option<Asset>),
+    Event(Option<Event>),
+    Transaction(Option<Transaction>),
+    Amendment(Option<Amendment>),
+}
+
+impl QueryResponse {
+    pub fn into_balance(self) -> Result<U256, Box<dyn Error>> {
+        match self {
+            QueryResponse::Balance(balance) => Ok(balance),
+            _ => Err("batch: response is not a balance".into()),
+        }
+    }
+}
+
+impl Service {
+    /// Runs `requests` concurrently with at most `max_in_flight` of them outstanding at a time.
+    /// Results are returned in the order of the requests; a failed request does not affect the
+    /// others.
+    pub async fn batch_query(
+        &self,
+        requests: &[QueryRequest],
+        max_in_flight: usize,
+    ) -> Vec<Result<QueryResponse, Box<dyn Error>>> {
+        run_batched(requests, max_in_flight, |request| self.run_query(request)).await
+    }
+
+    async fn run_query(&self, request: &QueryRequest) -> Result<QueryResponse, Box<dyn Error>> {
+        let response = match request {
+            QueryRequest::Balance {
+                unique_asset_id,
+                holder,
+            } => QueryResponse::Balance(self.int_get_balance(unique_asset_id, holder).await?),
+            QueryRequest::Supply(supply_id) => {
+                QueryResponse::Supply(self.int_get_supply(supply_id).await?)
+            }
+            QueryRequest::Asset {
+                contract_id,
+                asset_id,
+            } => QueryResponse::Asset(self.led_base_get_asset(contract_id, asset_id).await?),
+            QueryRequest::Event { stream, event_ix } => {
+                QueryResponse::Event(self.event_get(stream, *event_ix).await?)
+            }
+            QueryRequest::Transaction(tx_id) => {
+                QueryResponse::Transaction(self.get_tx(tx_id).await?)
+            }
+            QueryRequest::Amendment(amendment_id) => {
+                QueryResponse::Amendment(self.led_amen_get_amendment(amendment_id).await?)
+            }
+        };
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::Cell, time::Duration};
+    use tokio::time::sleep;
+
+    #[tokio::test]
+    async fn results_keep_the_order_of_the_items() {
+        // later items finish first
+        let delays = [40u64, 30, 20, 10, 0];
+        let finished = Cell::new(Vec::new());
+        let results = run_batched(&delays, 5, |delay| {
+            let finished = &finished;
+            async move {
+                sleep(Duration::from_millis(*delay)).await;
+                let mut order = finished.take();
+                order.push(*delay);
+                finished.set(order);
+                *delay
+            }
+        })
+        .await;
+
+        assert_eq!(finished.take(), vec![0, 10, 20, 30, 40]);
+        assert_eq!(results, delays);
+    }
+
+    #[tokio::test]
+    async fn a_failed_item_does_not_hide_the_others() {
+        let items = [1, 2, 3, 4];
+        let results = run_batched(&items, 2, |item| async move {
+            if *item == 2 {
+                Err(format!("item {} failed", item))
+            } else {
+                Ok(*item * 10)
+            }
+        })
+        .await;
+
+        assert_eq!(
+            results,
+            vec![Ok(10), Err("item 2 failed".to_string()), Ok(30), Ok(40)]
+        );
+    }
+
+    #[tokio::test]
+    async fn at_most_max_in_flight_items_run_at_once() {
+        let items = [0; 10];
+        let in_flight = Cell::new(0);
+        let peak = Cell::new(0);
+        run_batched(&items, 3, |_| {
+            let (in_flight, peak) = (&in_flight, &peak);
+            async move {
+                in_flight.set(in_flight.get() + 1);
+                peak.set(peak.get().max(in_flight.get()));
+                sleep(Duration::from_millis(5)).await;
+                in_flight.set(in_flight.get() - 1);
+            }
+        })
+        .await;
+
+        assert_eq!(peak.get(), 3);
+    }
+}
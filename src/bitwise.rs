@@ -0,0 +1,210 @@
+use crate::{amount::AmountError, AssetId, ContractId, Response, Service, UniqueAssetId, U256};
+use candid::Principal;
+use num_bigint::BigUint;
+use std::error::Error;
+
+/// Token positions held in a bitwise asset balance, where bit `n` (least significant first) set
+/// means the holder owns token `#n`.
+pub fn positions(mask: &U256) -> Vec<u64> {
+    let mask = mask.as_biguint();
+    (0..mask.bits()).filter(|&n| mask.bit(n)).collect()
+}
+
+pub fn mask(positions: &[u64]) -> Result<U256, AmountError> {
+    let mut mask = BigUint::default();
+    for &position in positions {
+        if position >= U256::BITS {
+            return Err(AmountError::Overflow);
+        }
+        mask.set_bit(position, true);
+    }
+    U256::new(mask)
+}
+
+/// Parses token positions such as `0-3,7`; an optional `#` before each position is ignored.
+pub fn parse_positions(text: &str) -> Result<U256, AmountError> {
+    let invalid = || AmountError::InvalidFormat(text.to_string());
+    let parse = |part: &str| part.trim().trim_start_matches('#').parse::<u64>();
+
+    let mut positions = Vec::new();
+    for part in text.split(',').filter(|part| !part.trim().is_empty()) {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (parse(first), parse(last));
+                let (Ok(first), Ok(last)) = (first, last) else {
+                    return Err(invalid());
+                };
+                if first > last || last >= U256::BITS {
+                    return Err(invalid());
+                }
+                positions.extend(first..=last);
+            }
+            None => positions.push(parse(part).map_err(|_| invalid())?),
+        }
+    }
+    if positions.is_empty() {
+        return Err(invalid());
+    }
+    mask(&positions)
+}
+
+/// Formats the positions of a bitwise balance as compact ranges, e.g. `#0-3, #7`.
+pub fn format_positions(mask: &U256) -> String {
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    for position in positions(mask) {
+        match ranges.last_mut() {
+            Some((_, last)) if *last + 1 == position => *last = position,
+            _ => ranges.push((position, position)),
+        }
+    }
+    if ranges.is_empty() {
+        return "-".to_string();
+    }
+
+    ranges
+        .iter()
+        .map(|&(first, last)| {
+            if first == last {
+                format!("#{}", first)
+            } else {
+                format!("#{}-{}", first, last)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Positions of `mask` that are not in `other`.
+pub fn difference(mask: &U256, other: &U256) -> U256 {
+    let mask = mask.as_biguint();
+    // clearing bits of a 256-bit value keeps it within 256 bits
+    U256::new(mask ^ (mask & other.as_biguint())).unwrap_or_default()
+}
+
+/// Positions in both `mask` and `other`.
+pub fn intersection(mask: &U256, other: &U256) -> U256 {
+    U256::new(mask.as_biguint() & other.as_biguint()).unwrap_or_default()
+}
+
+impl Service {
+    /// Token positions of a bitwise asset held by `holder`.
+    pub async fn bitwise_positions(
+        &self,
+        unique_asset_id: &UniqueAssetId,
+        holder: &Principal,
+    ) -> Result<Vec<u64>, Box<dyn Error>> {
+        let balance = self.int_get_balance(unique_asset_id, holder).await?;
+        Ok(positions(&balance))
+    }
+
+    /// Issues the token positions of `positions` to the issuer, after checking that none of them
+    /// was issued before; the issued tokens of a bitwise asset are the bitmask of all positions.
+    pub async fn issue_positions(
+        &self,
+        contract_id: &ContractId,
+        asset_id: &AssetId,
+        positions: &U256,
+    ) -> Result<Response, Box<dyn Error>> {
+        let issued = self.int_get_tokens(contract_id, asset_id).await?;
+        let reissued = intersection(positions, &issued);
+        if !reissued.is_zero() {
+            return Err(format!("token(s) {} already issued", format_positions(&reissued)).into());
+        }
+        self.led_base_issue_tokens(contract_id, asset_id, positions)
+            .await
+    }
+
+    /// Transfers the token positions of `positions` from `sender`, the current identity, after
+    /// checking that it holds all of them.
+    pub async fn transfer_positions(
+        &self,
+        unique_asset_id: &UniqueAssetId,
+        sender: &Principal,
+        receiver: &Principal,
+        positions: &U256,
+    ) -> Result<Response, Box<dyn Error>> {
+        let held = self.int_get_balance(unique_asset_id, sender).await?;
+        let missing = difference(positions, &held);
+        if !missing.is_zero() {
+            return Err(format!(
+                "{} does not hold token(s) {}",
+                sender,
+                format_positions(&missing)
+            )
+            .into());
+        }
+        self.int_transfer_tokens(unique_asset_id, receiver, positions)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(text: &str) -> Vec<u64> {
+        positions(&parse_positions(text).unwrap())
+    }
+
+    #[test]
+    fn parses_positions_and_ranges() {
+        assert_eq!(parsed("0-3,7"), vec![0, 1, 2, 3, 7]);
+        assert_eq!(parsed("#0-#3, #7"), vec![0, 1, 2, 3, 7]);
+        assert_eq!(parsed("5"), vec![5]);
+        assert_eq!(parsed("4-4"), vec![4]);
+        assert_eq!(parse_positions("0-3").unwrap(), U256::from(0b1111u8));
+    }
+
+    #[test]
+    fn overlapping_and_repeated_positions_are_merged() {
+        assert_eq!(parsed("1-3,2-4,3"), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn ignores_empty_parts() {
+        assert_eq!(parsed(",1,,2,"), vec![1, 2]);
+    }
+
+    #[test]
+    fn accepts_the_highest_position() {
+        assert_eq!(parsed("255"), vec![255]);
+        assert_eq!(parsed("250-255").len(), 6);
+        assert_eq!(parse_positions("0-255").unwrap(), U256::max_value());
+    }
+
+    #[test]
+    fn rejects_positions_beyond_256_bits() {
+        assert!(matches!(parse_positions("256"), Err(AmountError::Overflow)));
+        assert!(matches!(
+            parse_positions("250-256"),
+            Err(AmountError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        for text in ["", " , ", "3-1", "a", "1-", "-1", "1-2-3", "1.5", "#"] {
+            assert!(
+                matches!(parse_positions(text), Err(AmountError::InvalidFormat(_))),
+                "{:?} was accepted",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn formats_ranges() {
+        let mask = parse_positions("0-3,7,9-10").unwrap();
+        assert_eq!(format_positions(&mask), "#0-3, #7, #9-10");
+        assert_eq!(format_positions(&U256::zero()), "-");
+        assert_eq!(parse_positions(&format_positions(&mask)).unwrap(), mask);
+    }
+
+    #[test]
+    fn set_operations() {
+        let mask = parse_positions("0-7").unwrap();
+        let other = parse_positions("4-11").unwrap();
+        assert_eq!(positions(&difference(&mask, &other)), vec![0, 1, 2, 3]);
+        assert_eq!(positions(&intersection(&mask, &other)), vec![4, 5, 6, 7]);
+    }
+}
@@ -0,0 +1,246 @@
+use candid::{IDLArgs, IDLValue};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Reads that are cached by default with their time to live; `None` never expires.
+pub const CACHED_READS: [(&str, Option<Duration>); 6] = [
+    ("mng_contract_name", None),
+    ("mng_contract_version", Some(Duration::from_secs(3600))),
+    ("int_get_ledger_contract_id", None),
+    ("int_get_decimal_ptr", None),
+    ("led_base_get_asset", Some(Duration::from_secs(300))),
+    ("led_amen_get_amendment", Some(Duration::from_secs(300))),
+];
+
+/// Updates of this client that change a cached read, which is then dropped entirely.
+const INVALIDATED_BY: [(&str, &str); 1] = [("int_set_contract", "int_get_ledger_contract_id")];
+
+#[derive(Clone, Copy)]
+pub enum CacheTtl {
+    Forever,
+    For(Duration),
+}
+
+/// Parses a `METHOD=SECONDS` argument; `forever` never expires and 0 disables caching.
+pub fn parse_cache_ttl(arg: &str) -> Result<(String, Option<CacheTtl>), String> {
+    let (method_name, ttl) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected METHOD=SECONDS, got '{}'", arg))?;
+    let ttl = match ttl {
+        "forever" => Some(CacheTtl::Forever),
+        "0" => None,
+        seconds => Some(CacheTtl::For(Duration::from_secs(
+            seconds.parse().map_err(|e| format!("{}: {}", seconds, e))?,
+        ))),
+    };
+    Ok((method_name.to_string(), ttl))
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    /// Candid-encoded response, hex
+    response: String,
+    /// Unix time in seconds; `None` never expires
+    expires_at: Option<u64>,
+}
+
+/// Read-through cache of query responses keyed by method name and Candid-encoded arguments.
+/// Entries with a time to live are dropped by `invalidate_after`, which the service calls after
+/// every successful update; changes made by other clients are picked up through `invalidate`
+/// when the corresponding events are seen.
+pub struct ResponseCache {
+    ttls: HashMap<String, CacheTtl>,
+    entries: RefCell<HashMap<String, HashMap<String, CacheEntry>>>,
+    file_path: Option<PathBuf>,
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        let ttls = CACHED_READS
+            .iter()
+            .map(|(method_name, ttl)| {
+                let ttl = ttl.map_or(CacheTtl::Forever, CacheTtl::For);
+                (method_name.to_string(), ttl)
+            })
+            .collect();
+
+        Self {
+            ttls,
+            entries: RefCell::new(HashMap::new()),
+            file_path: None,
+        }
+    }
+}
+
+impl ResponseCache {
+    /// Cache persisted in `file_path`; a missing file starts empty.
+    pub fn open<P: AsRef<Path>>(file_path: P) -> Result<Self, Box<dyn Error>> {
+        let file_path = file_path.as_ref().to_path_buf();
+        let entries = match fs::read_to_string(&file_path) {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            entries: RefCell::new(entries),
+            file_path: Some(file_path),
+            ..Self::default()
+        })
+    }
+
+    /// Overrides the time to live of `method_name`; `None` stops caching it.
+    pub fn set_ttl(&mut self, method_name: &str, ttl: Option<CacheTtl>) {
+        match ttl {
+            Some(ttl) => self.ttls.insert(method_name.to_string(), ttl),
+            None => self.ttls.remove(method_name),
+        };
+    }
+
+    pub fn is_cached(&self, method_name: &str) -> bool {
+        self.ttls.contains_key(method_name)
+    }
+
+    pub fn get(&self, method_name: &str, args: &[u8]) -> Option<Vec<u8>> {
+        let entries = self.entries.borrow();
+        let entry = entries.get(method_name)?.get(&hex::encode(args))?;
+        if entry
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= now())
+        {
+            return None;
+        }
+        hex::decode(&entry.response).ok()
+    }
+
+    /// Caches a response, unless it is a missing value (`null` of an `opt`): a ledger contract
+    /// or asset that does not exist yet may be created at any time.
+    pub fn insert(&self, method_name: &str, args: &[u8], response: &[u8]) {
+        let Some(ttl) = self.ttls.get(method_name) else {
+            return;
+        };
+        if is_absent(response) {
+            return;
+        }
+        let expires_at = match ttl {
+            CacheTtl::Forever => None,
+            CacheTtl::For(ttl) => Some(now() + ttl.as_secs()),
+        };
+        self.entries
+            .borrow_mut()
+            .entry(method_name.to_string())
+            .or_default()
+            .insert(
+                hex::encode(args),
+                CacheEntry {
+                    response: hex::encode(response),
+                    expires_at,
+                },
+            );
+    }
+
+    pub fn invalidate(&self, method_name: &str, args: &[u8]) {
+        if let Some(entries) = self.entries.borrow_mut().get_mut(method_name) {
+            entries.remove(&hex::encode(args));
+        }
+    }
+
+    /// Drops all entries that are not immutable, and all entries of reads changed by
+    /// `update_method`. Called after every successful update.
+    pub fn invalidate_after(&self, update_method: &str) {
+        let mut entries = self.entries.borrow_mut();
+        for (update, read) in INVALIDATED_BY {
+            if update == update_method {
+                entries.remove(read);
+            }
+        }
+        for entries in entries.values_mut() {
+            entries.retain(|_, entry| entry.expires_at.is_none());
+        }
+    }
+
+    /// Writes the unexpired entries to the cache file, if any.
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let Some(file_path) = &self.file_path else {
+            return Ok(());
+        };
+        let now = now();
+        let mut entries = self.entries.borrow_mut();
+        for method_entries in entries.values_mut() {
+            method_entries.retain(|_, entry| entry.expires_at.is_none_or(|e| e > now));
+        }
+        fs::write(file_path, serde_json::to_string_pretty(&*entries)?)?;
+        Ok(())
+    }
+}
+
+fn is_absent(response: &[u8]) -> bool {
+    IDLArgs::from_bytes(response)
+        .is_ok_and(|response| matches!(response.args[..], [IDLValue::None]))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::{Encode, Nat};
+
+    fn ledger_id_args(ledger_id: u16) -> Vec<u8> {
+        Encode!(&ledger_id).unwrap()
+    }
+
+    #[test]
+    fn missing_values_are_not_cached() {
+        let cache = ResponseCache::default();
+        let args = ledger_id_args(1);
+        cache.insert(
+            "int_get_ledger_contract_id",
+            &args,
+            &Encode!(&None::<Nat>).unwrap(),
+        );
+        assert!(cache.get("int_get_ledger_contract_id", &args).is_none());
+
+        let response = Encode!(&Some(Nat::from(42u8))).unwrap();
+        cache.insert("int_get_ledger_contract_id", &args, &response);
+        assert_eq!(
+            cache.get("int_get_ledger_contract_id", &args),
+            Some(response)
+        );
+    }
+
+    #[test]
+    fn set_contract_drops_ledger_contract_ids() {
+        let cache = ResponseCache::default();
+        let response = Encode!(&Some(Nat::from(42u8))).unwrap();
+        cache.insert("int_get_ledger_contract_id", &ledger_id_args(1), &response);
+        cache.insert(
+            "int_get_decimal_ptr",
+            &[],
+            &Encode!(&Nat::from(8u8)).unwrap(),
+        );
+
+        cache.invalidate_after("int_transfer_tokens");
+        assert!(cache
+            .get("int_get_ledger_contract_id", &ledger_id_args(1))
+            .is_some());
+
+        cache.invalidate_after("int_set_contract");
+        assert!(cache
+            .get("int_get_ledger_contract_id", &ledger_id_args(1))
+            .is_none());
+        assert!(cache.get("int_get_decimal_ptr", &[]).is_some());
+    }
+}
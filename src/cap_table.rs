@@ -0,0 +1,904 @@
+use crate::{
+    batch::{QueryRequest, QueryResponse},
+    bitwise,
+    events::{self, Event, EventStream},
+    pricing,
+    registry::AssetRegistry,
+    report::{csv_field, ReportFormat},
+    AccountId, AccountUpdate, EventType, Service, Transaction, UniqueAssetId, U256,
+};
+use candid::Principal;
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use serde::Serialize;
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    error::Error,
+    fmt::Write,
+};
+
+/// A ledger account as seen in the `AccountUpdate` event stream.
+pub struct LedgerAccount {
+    pub account_id: AccountId,
+    /// Asset of the account, if a transaction touching it names one
+    pub unique_asset_id: Option<UniqueAssetId>,
+    /// `current_amount` of the latest update
+    pub balance: U256,
+    /// Whether the account was credited with a fee
+    pub fee_wallet: bool,
+    /// Principals named in the transactions touching the account
+    pub parties: Vec<Principal>,
+}
+
+/// Accounts of the ledger with the principals their transactions involve.
+pub struct Discovery {
+    pub accounts: Vec<LedgerAccount>,
+}
+
+impl Discovery {
+    /// Principals named in the transactions of the accounts of an asset.
+    pub fn parties(&self, unique_asset_id: &UniqueAssetId) -> Vec<Principal> {
+        let mut parties = Vec::new();
+        for account in &self.accounts {
+            if account.unique_asset_id == Some(*unique_asset_id) {
+                for party in &account.parties {
+                    if !parties.contains(party) {
+                        parties.push(*party);
+                    }
+                }
+            }
+        }
+        parties
+    }
+}
+
+/// What `discover_accounts` reads from the canister, keyed by event index within each stream.
+#[derive(Default)]
+struct LedgerHistory {
+    updates: Vec<(u64, AccountUpdate)>,
+    transactions: Vec<Transaction>,
+    created: HashMap<u64, UniqueAssetId>,
+    destroyed: HashMap<u64, UniqueAssetId>,
+    /// Owner and desired address of the supply of each `SupplyUpdate` event
+    supply_parties: HashMap<u64, Vec<Principal>>,
+    /// Affected address of each `LimitConsumed` event
+    limit_parties: HashMap<u64, Principal>,
+    issuers: HashMap<UniqueAssetId, Principal>,
+    /// Non-zero fees ever set
+    fee_amounts: HashSet<(UniqueAssetId, U256)>,
+}
+
+/// Reads every `AccountUpdate` event and attributes the accounts to assets and principals
+/// through the transactions that made them.
+///
+/// `AccountUpdate` events only carry an account id, and `get_tx` only lists the events of a
+/// transaction without a sender or receiver. The principals of a transaction are the ones its
+/// other events name: the owner and desired address of a consumed supply, the affected address
+/// of a consumed limit, and the issuer of the asset of a `TokensCreated` or `TokensDestroyed`
+/// event. See `attribute` for how accounts get their asset.
+pub async fn discover_accounts(
+    service: &Service,
+    max_in_flight: usize,
+) -> Result<Discovery, Box<dyn Error>> {
+    let mut history = LedgerHistory::default();
+    for (event_ix, event) in
+        events::fetch_events(service, EventStream::AccountUpdate, max_in_flight).await?
+    {
+        if let Event::AccountUpdate(update) = event {
+            history.updates.push((event_ix, update));
+        }
+    }
+    history.transactions = events::fetch_transactions(
+        service,
+        |e| matches!(e.event_type, EventType::AccountUpdate),
+        history.updates.len(),
+        max_in_flight,
+    )
+    .await?;
+
+    // the events of the transactions that name an asset or a principal
+    let mut requests = Vec::new();
+    for e in history.transactions.iter().flat_map(|tx| &tx.events) {
+        let stream = match (&e.event_type, &e.contract_id) {
+            (EventType::TokensCreated, _) => EventStream::TokensCreated,
+            (EventType::TokensDestroyed, _) => EventStream::TokensDestroyed,
+            (EventType::SupplyUpdate, _) => EventStream::SupplyUpdate,
+            (EventType::LimitConsumed, Some(contract_id)) => {
+                EventStream::LimitConsumed(contract_id.clone())
+            }
+            _ => continue,
+        };
+        requests.push(QueryRequest::Event {
+            stream,
+            event_ix: e.event_ix,
+        });
+    }
+    let mut supplies = Vec::new();
+    for (request, result) in requests
+        .iter()
+        .zip(service.batch_query(&requests, max_in_flight).await)
+    {
+        let QueryRequest::Event { event_ix, .. } = request else {
+            continue;
+        };
+        match result? {
+            QueryResponse::Event(Some(Event::TokensCreated(e))) => {
+                history.created.insert(*event_ix, e.unique_asset_id);
+            }
+            QueryResponse::Event(Some(Event::TokensDestroyed(e))) => {
+                history.destroyed.insert(*event_ix, e.unique_asset_id);
+            }
+            QueryResponse::Event(Some(Event::SupplyUpdate(e))) => {
+                supplies.push((*event_ix, e.supply_id));
+            }
+            QueryResponse::Event(Some(Event::LimitConsumed(e))) => {
+                history.limit_parties.insert(*event_ix, e.affected_address);
+            }
+            _ => {}
+        }
+    }
+
+    let requests = supplies
+        .iter()
+        .map(|(_, supply_id)| QueryRequest::Supply(supply_id.clone()))
+        .collect::<Vec<_>>();
+    for ((event_ix, _), result) in supplies
+        .iter()
+        .zip(service.batch_query(&requests, max_in_flight).await)
+    {
+        if let QueryResponse::Supply(Some(supply)) = result? {
+            let mut parties = vec![supply.owner];
+            parties.extend(supply.parameters.and_then(|p| p.desired_address));
+            history.supply_parties.insert(*event_ix, parties);
+        }
+    }
+
+    let named_assets = history
+        .created
+        .values()
+        .chain(history.destroyed.values())
+        .copied()
+        .collect::<HashSet<_>>();
+    for unique_asset_id in named_assets {
+        let (asset_id, ledger_id) = unique_asset_id.decompose();
+        let Some(contract_id) = service.int_get_ledger_contract_id(&ledger_id).await? else {
+            continue;
+        };
+        if let Some(asset) = service.led_base_get_asset(&contract_id, &asset_id).await? {
+            history.issuers.insert(unique_asset_id, asset.issuer);
+        }
+    }
+
+    history.fee_amounts = pricing::history(service, None, max_in_flight)
+        .await?
+        .into_iter()
+        .filter(|change| !change.fee_amount.is_zero())
+        .map(|change| (change.unique_asset_id, change.fee_amount))
+        .collect();
+
+    Ok(attribute(history))
+}
+
+/// Attributes the accounts of `history` to assets and principals.
+///
+/// The accounts of a transaction with a `TokensCreated` or `TokensDestroyed` event belong to
+/// that asset, and the asset is carried over to the other accounts of every transfer involving
+/// such an account. Warps (transactions with `SupplyUpdate` events) move two assets, so their
+/// accounts only get an asset from other transactions. In a transfer with several credits, a
+/// credit equal to a fee ever set on the asset goes to a fee wallet, unless every credit matches.
+fn attribute(history: LedgerHistory) -> Discovery {
+    let mut balances: HashMap<AccountId, U256> = HashMap::new();
+    let mut updated = HashMap::new();
+    for (event_ix, update) in &history.updates {
+        balances.insert(update.account_id.clone(), update.current_amount.clone());
+        updated.insert(*event_ix, update);
+    }
+
+    // account updates of every transaction: (account, credited amount or None for a debit)
+    let moves = |tx: &Transaction| {
+        tx.events
+            .iter()
+            .filter(|e| matches!(e.event_type, EventType::AccountUpdate))
+            .filter_map(|e| updated.get(&e.event_ix))
+            .map(|update| {
+                let credit = update
+                    .current_amount
+                    .checked_sub(&update.previous_amount)
+                    .ok()
+                    .filter(|credit| !credit.is_zero());
+                (update.account_id.clone(), credit)
+            })
+            .collect::<Vec<_>>()
+    };
+    let is_warp = |tx: &Transaction| {
+        tx.events
+            .iter()
+            .any(|e| matches!(e.event_type, EventType::SupplyUpdate))
+    };
+    let named_asset = |tx: &Transaction| {
+        tx.events.iter().find_map(|e| match e.event_type {
+            EventType::TokensCreated => history.created.get(&e.event_ix),
+            EventType::TokensDestroyed => history.destroyed.get(&e.event_ix),
+            _ => None,
+        })
+    };
+
+    let mut parties: HashMap<AccountId, Vec<Principal>> = HashMap::new();
+    for tx in &history.transactions {
+        let mut named = Vec::new();
+        for e in &tx.events {
+            match e.event_type {
+                EventType::SupplyUpdate => named.extend(
+                    history
+                        .supply_parties
+                        .get(&e.event_ix)
+                        .into_iter()
+                        .flatten(),
+                ),
+                EventType::LimitConsumed => named.extend(history.limit_parties.get(&e.event_ix)),
+                _ => {}
+            }
+        }
+        named.extend(named_asset(tx).and_then(|u| history.issuers.get(u)));
+        for (account_id, _) in moves(tx) {
+            let account_parties = parties.entry(account_id).or_default();
+            for party in &named {
+                if !account_parties.contains(party) {
+                    account_parties.push(*party);
+                }
+            }
+        }
+    }
+
+    let mut assets: HashMap<AccountId, UniqueAssetId> = HashMap::new();
+    for tx in &history.transactions {
+        if let Some(unique_asset_id) = named_asset(tx) {
+            for (account_id, _) in moves(tx) {
+                assets.insert(account_id, *unique_asset_id);
+            }
+        }
+    }
+    // transfers carry the asset of any known account to the others, until nothing changes
+    loop {
+        let mut changed = false;
+        for tx in history.transactions.iter().filter(|tx| !is_warp(tx)) {
+            let moves = moves(tx);
+            let known = moves
+                .iter()
+                .filter_map(|(account_id, _)| assets.get(account_id))
+                .collect::<HashSet<_>>();
+            let [unique_asset_id] = known.into_iter().collect::<Vec<_>>()[..] else {
+                continue;
+            };
+            let unique_asset_id = *unique_asset_id;
+            for (account_id, _) in moves {
+                if let Entry::Vacant(entry) = assets.entry(account_id) {
+                    entry.insert(unique_asset_id);
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut fee_wallets = HashSet::new();
+    for tx in history.transactions.iter().filter(|tx| !is_warp(tx)) {
+        let credits = moves(tx)
+            .into_iter()
+            .filter_map(|(account_id, credit)| Some((account_id, credit?)))
+            .collect::<Vec<_>>();
+        let fees = credits
+            .iter()
+            .filter(|(account_id, credit)| {
+                assets.get(account_id).is_some_and(|unique_asset_id| {
+                    history
+                        .fee_amounts
+                        .contains(&(*unique_asset_id, credit.clone()))
+                })
+            })
+            .collect::<Vec<_>>();
+        if credits.len() > 1 && fees.len() < credits.len() {
+            fee_wallets.extend(fees.into_iter().map(|(account_id, _)| account_id.clone()));
+        }
+    }
+
+    let mut accounts = balances
+        .into_iter()
+        .map(|(account_id, balance)| LedgerAccount {
+            unique_asset_id: assets.get(&account_id).copied(),
+            fee_wallet: fee_wallets.contains(&account_id),
+            parties: parties.remove(&account_id).unwrap_or_default(),
+            account_id,
+            balance,
+        })
+        .collect::<Vec<_>>();
+    accounts.sort_by(|a, b| a.account_id.cmp(&b.account_id));
+
+    Discovery { accounts }
+}
+
+/// Pairs holders with accounts of the same balance, as `(holder index, account index)`. A pair
+/// is only made when it is the only one possible for both sides: among the candidates of equal
+/// balance, those named in the account's transactions are preferred, and holders or accounts
+/// that still have several candidates stay unpaired rather than being matched by chance.
+fn pair_holders(held: &[(Principal, U256)], accounts: &[&LedgerAccount]) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    let mut free_holders = (0..held.len()).collect::<Vec<_>>();
+    let mut free_accounts = (0..accounts.len()).collect::<Vec<_>>();
+
+    // holders an account could belong to, narrowed to those its transactions name
+    let holder_options = |account: usize, free_holders: &[usize]| {
+        let account = accounts[account];
+        let equal = free_holders
+            .iter()
+            .copied()
+            .filter(|h| held[*h].1 == account.balance)
+            .collect::<Vec<_>>();
+        let named = equal
+            .iter()
+            .copied()
+            .filter(|h| account.parties.contains(&held[*h].0))
+            .collect::<Vec<_>>();
+        if named.is_empty() {
+            equal
+        } else {
+            named
+        }
+    };
+
+    loop {
+        let mut paired = None;
+        for holder in free_holders.iter().copied() {
+            let (principal, balance) = &held[holder];
+            let equal = free_accounts
+                .iter()
+                .copied()
+                .filter(|a| accounts[*a].balance == *balance)
+                .collect::<Vec<_>>();
+            let named = equal
+                .iter()
+                .copied()
+                .filter(|a| accounts[*a].parties.contains(principal))
+                .collect::<Vec<_>>();
+            let options = if named.is_empty() { equal } else { named };
+            if let [account] = options[..] {
+                if holder_options(account, &free_holders) == [holder] {
+                    paired = Some((holder, account));
+                    break;
+                }
+            }
+        }
+        let Some((holder, account)) = paired else {
+            break;
+        };
+        pairs.push((holder, account));
+        free_holders.retain(|h| *h != holder);
+        free_accounts.retain(|a| *a != account);
+    }
+    pairs
+}
+
+#[derive(Serialize)]
+pub struct CapTableRow {
+    pub holder: String,
+    pub principal: String,
+    /// Account of the holder, if only one account fits it
+    pub account_id: Option<String>,
+    /// `holder` or `fee wallet`
+    pub role: String,
+    pub balance: String,
+    /// Share of the outstanding tokens in percent, two decimals
+    pub share: String,
+}
+
+/// An account of the asset that could not be paired with a holder.
+#[derive(Serialize)]
+pub struct UnresolvedAccount {
+    pub account_id: String,
+    pub balance: String,
+    pub share: String,
+    pub fee_wallet: bool,
+    /// Unpaired holders with the same balance, one of which may own the account
+    pub candidates: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct CapTable {
+    pub asset: String,
+    pub unique_asset_id: String,
+    pub outstanding: String,
+    pub rows: Vec<CapTableRow>,
+    pub unresolved: Vec<UnresolvedAccount>,
+    /// Outstanding tokens in neither a holder row nor an unresolved account
+    pub unattributed: String,
+}
+
+impl CapTable {
+    /// Builds the cap table of an asset, sorted by balance. The holders are `candidates` and
+    /// every principal named in the transactions of the asset's accounts; each of them with a
+    /// live balance gets a row, paired with its account where `pair_holders` finds exactly one.
+    /// Accounts left over are listed as unresolved, with the unpaired holders of the same balance
+    /// as their candidates. Shares of bitwise assets are counted in tokens rather than by their
+    /// bitmask.
+    pub async fn fetch(
+        service: &Service,
+        registry: &AssetRegistry,
+        unique_asset_id: &UniqueAssetId,
+        discovery: &Discovery,
+        candidates: &[Principal],
+        holder_label: impl Fn(&Principal) -> String,
+        max_in_flight: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        let (asset_id, ledger_id) = unique_asset_id.decompose();
+        let contract_id = service
+            .int_get_ledger_contract_id(&ledger_id)
+            .await?
+            .ok_or("ledger contract not found")?;
+        let outstanding = service.int_get_tokens(&contract_id, &asset_id).await?;
+
+        let mut holders = candidates.to_vec();
+        holders.extend(discovery.parties(unique_asset_id));
+        let mut seen = HashSet::new();
+        holders.retain(|holder| seen.insert(*holder));
+
+        let requests = holders
+            .iter()
+            .map(|holder| QueryRequest::Balance {
+                unique_asset_id: *unique_asset_id,
+                holder: *holder,
+            })
+            .collect::<Vec<_>>();
+        let balances = service
+            .batch_query(&requests, max_in_flight)
+            .await
+            .into_iter()
+            .map(|result| result.and_then(QueryResponse::into_balance))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut held: Vec<(Principal, U256)> = holders
+            .into_iter()
+            .zip(balances)
+            .filter(|(_, balance)| !balance.is_zero())
+            .collect();
+        held.sort_by(|a, b| b.1.cmp(&a.1));
+
+        let accounts = discovery
+            .accounts
+            .iter()
+            .filter(|a| a.unique_asset_id == Some(*unique_asset_id) && !a.balance.is_zero())
+            .collect::<Vec<_>>();
+        let pairs = pair_holders(&held, &accounts);
+        let account_of = |holder: usize| {
+            pairs
+                .iter()
+                .find(|(h, _)| *h == holder)
+                .map(|(_, a)| accounts[*a])
+        };
+
+        let bitwise = registry.is_bitwise(unique_asset_id);
+        let measure = |amount: &U256| {
+            if bitwise {
+                BigUint::from(bitwise::positions(amount).len())
+            } else {
+                amount.as_biguint().clone()
+            }
+        };
+        let format = |amount: &U256| registry.format_amount(unique_asset_id, amount);
+        let mut accounted = U256::zero();
+        let mut add = |amount: &U256| -> Result<(), Box<dyn Error>> {
+            accounted = if bitwise {
+                U256::new(accounted.as_biguint() | amount.as_biguint())?
+            } else {
+                accounted.checked_add(amount)?
+            };
+            Ok(())
+        };
+
+        let mut rows = Vec::new();
+        for (holder, (principal, balance)) in held.iter().enumerate() {
+            let account = account_of(holder);
+            add(balance)?;
+            rows.push(CapTableRow {
+                holder: holder_label(principal),
+                principal: principal.to_text(),
+                account_id: account.map(|a| a.account_id.to_string()),
+                role: if account.is_some_and(|a| a.fee_wallet) {
+                    "fee wallet"
+                } else {
+                    "holder"
+                }
+                .to_string(),
+                balance: format(balance),
+                share: share(&measure(balance), &measure(&outstanding)),
+            });
+        }
+
+        // an unpaired holder's tokens sit in one of the unpaired accounts of its balance
+        let unpaired = (0..held.len())
+            .filter(|h| account_of(*h).is_none())
+            .collect::<Vec<_>>();
+        let mut uncovered = unpaired.clone();
+        let mut unresolved = Vec::new();
+        for (ix, account) in accounts.iter().enumerate() {
+            if pairs.iter().any(|(_, a)| *a == ix) {
+                continue;
+            }
+            match uncovered.iter().position(|h| held[*h].1 == account.balance) {
+                Some(position) => {
+                    uncovered.remove(position);
+                }
+                None => add(&account.balance)?,
+            }
+            unresolved.push(UnresolvedAccount {
+                account_id: account.account_id.to_string(),
+                balance: format(&account.balance),
+                share: share(&measure(&account.balance), &measure(&outstanding)),
+                fee_wallet: account.fee_wallet,
+                candidates: unpaired
+                    .iter()
+                    .filter(|h| held[**h].1 == account.balance)
+                    .map(|h| held[*h].0.to_text())
+                    .collect(),
+            });
+        }
+        let unattributed = if bitwise {
+            bitwise::difference(&outstanding, &accounted)
+        } else {
+            outstanding.checked_sub(&accounted).unwrap_or_default()
+        };
+
+        Ok(Self {
+            asset: registry.label(unique_asset_id),
+            unique_asset_id: unique_asset_id.to_string(),
+            outstanding: format(&outstanding),
+            rows,
+            unresolved,
+            unattributed: format(&unattributed),
+        })
+    }
+
+    pub fn render(&self, format: ReportFormat) -> Result<String, Box<dyn Error>> {
+        match format {
+            ReportFormat::Table => Ok(self.render_table()),
+            ReportFormat::Csv => Ok(self.render_csv()),
+            ReportFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+        }
+    }
+
+    fn render_table(&self) -> String {
+        use prettytable::{Cell, Row, Table};
+
+        let mut table = Table::new();
+        table.add_row(Row::new(vec![
+            Cell::new("Holder"),
+            Cell::new("Principal"),
+            Cell::new("Account"),
+            Cell::new("Role"),
+            Cell::new("Balance"),
+            Cell::new("Share"),
+        ]));
+        for row in &self.rows {
+            table.add_row(Row::new(vec![
+                Cell::new(&row.holder),
+                Cell::new(&row.principal),
+                Cell::new(row.account_id.as_deref().unwrap_or("?")),
+                Cell::new(&row.role),
+                Cell::new(&row.balance),
+                Cell::new(&row.share),
+            ]));
+        }
+        for account in &self.unresolved {
+            let principal = match &account.candidates[..] {
+                [] => "unresolved".to_string(),
+                candidates => format!("one of {}", candidates.join(", ")),
+            };
+            table.add_row(Row::new(vec![
+                Cell::new("unresolved"),
+                Cell::new(&principal),
+                Cell::new(&account.account_id),
+                Cell::new(unresolved_role(account)),
+                Cell::new(&account.balance),
+                Cell::new(&account.share),
+            ]));
+        }
+        table.add_row(Row::new(vec![
+            Cell::new("unattributed"),
+            Cell::new(""),
+            Cell::new(""),
+            Cell::new(""),
+            Cell::new(&self.unattributed),
+            Cell::new(""),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("outstanding"),
+            Cell::new(""),
+            Cell::new(""),
+            Cell::new(""),
+            Cell::new(&self.outstanding),
+            Cell::new("100.00%"),
+        ]));
+
+        table.to_string()
+    }
+
+    fn render_csv(&self) -> String {
+        let mut csv = String::from(
+            "asset,unique_asset_id,holder,principal,account_id,role,balance,share,candidates\n",
+        );
+        for row in &self.rows {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{},",
+                csv_field(&self.asset),
+                self.unique_asset_id,
+                csv_field(&row.holder),
+                row.principal,
+                row.account_id.as_deref().unwrap_or_default(),
+                row.role,
+                csv_field(&row.balance),
+                row.share
+            );
+        }
+        for account in &self.unresolved {
+            let _ = writeln!(
+                csv,
+                "{},{},unresolved,,{},{},{},{},{}",
+                csv_field(&self.asset),
+                self.unique_asset_id,
+                account.account_id,
+                unresolved_role(account),
+                csv_field(&account.balance),
+                account.share,
+                account.candidates.join(" ")
+            );
+        }
+        csv
+    }
+}
+
+fn unresolved_role(account: &UnresolvedAccount) -> &'static str {
+    if account.fee_wallet {
+        "fee wallet"
+    } else {
+        "holder"
+    }
+}
+
+fn share(amount: &BigUint, total: &BigUint) -> String {
+    if *total == BigUint::default() {
+        return "-".to_string();
+    }
+    // basis points, rounded down
+    let basis_points = (amount * BigUint::from(10_000u32) / total)
+        .to_u64()
+        .unwrap_or(u64::MAX);
+    format!("{}.{:02}%", basis_points / 100, basis_points % 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TransactionEvent;
+    use candid::Nat;
+
+    fn asset(asset_id: u8) -> UniqueAssetId {
+        UniqueAssetId::compose(&Nat::from(asset_id), 1).unwrap()
+    }
+
+    fn principal(id: u8) -> Principal {
+        Principal::from_slice(&[id])
+    }
+
+    fn event(event_type: EventType, event_ix: u64) -> TransactionEvent {
+        TransactionEvent {
+            contract_id: None,
+            ledger_id: Some(1),
+            event_ix,
+            event_type,
+        }
+    }
+
+    fn tx(tx_id: u32, events: Vec<TransactionEvent>) -> Transaction {
+        Transaction {
+            occured_on: 0,
+            tx_id: Nat::from(tx_id),
+            events,
+        }
+    }
+
+    /// Adds an account update to `history` and returns its event in a transaction.
+    fn update(
+        history: &mut LedgerHistory,
+        account_id: u8,
+        previous: u32,
+        current: u32,
+    ) -> TransactionEvent {
+        let event_ix = history.updates.len() as u64;
+        history.updates.push((
+            event_ix,
+            AccountUpdate {
+                account_id: Nat::from(account_id),
+                previous_amount: U256::from(previous),
+                current_amount: U256::from(current),
+            },
+        ));
+        event(EventType::AccountUpdate, event_ix)
+    }
+
+    fn account(discovery: &Discovery, account_id: u8) -> &LedgerAccount {
+        discovery
+            .accounts
+            .iter()
+            .find(|a| a.account_id == account_id)
+            .unwrap()
+    }
+
+    /// Issue of 100 tokens of asset 1 to account 1.
+    fn issued(history: &mut LedgerHistory) {
+        history.created.insert(0, asset(1));
+        history.issuers.insert(asset(1), principal(1));
+        let credit = update(history, 1, 0, 100);
+        history
+            .transactions
+            .push(tx(0, vec![event(EventType::TokensCreated, 0), credit]));
+    }
+
+    #[test]
+    fn issues_name_the_asset_and_issuer_of_their_accounts() {
+        let mut history = LedgerHistory::default();
+        issued(&mut history);
+        let discovery = attribute(history);
+
+        let issued = account(&discovery, 1);
+        assert!(issued.unique_asset_id == Some(asset(1)));
+        assert_eq!(issued.balance, U256::from(100u8));
+        assert_eq!(issued.parties, vec![principal(1)]);
+        assert!(!issued.fee_wallet);
+        assert_eq!(discovery.parties(&asset(1)), vec![principal(1)]);
+        assert!(discovery.parties(&asset(2)).is_empty());
+    }
+
+    #[test]
+    fn transfers_carry_the_asset_on_but_warps_do_not() {
+        let mut history = LedgerHistory::default();
+        issued(&mut history);
+        // account 1 pays account 2, which later pays account 3
+        let events = vec![
+            update(&mut history, 1, 100, 60),
+            update(&mut history, 2, 0, 40),
+        ];
+        history.transactions.push(tx(1, events));
+        let events = vec![
+            update(&mut history, 2, 40, 30),
+            update(&mut history, 3, 0, 10),
+        ];
+        history.transactions.push(tx(2, events));
+        // a warp between account 3 and account 4 of another asset
+        history
+            .supply_parties
+            .insert(0, vec![principal(7), principal(8)]);
+        let events = vec![
+            event(EventType::SupplyUpdate, 0),
+            update(&mut history, 3, 10, 5),
+            update(&mut history, 4, 0, 5),
+        ];
+        history.transactions.push(tx(3, events));
+        let discovery = attribute(history);
+
+        for account_id in [1, 2, 3] {
+            assert!(account(&discovery, account_id).unique_asset_id == Some(asset(1)));
+        }
+        let warped = account(&discovery, 4);
+        assert!(warped.unique_asset_id.is_none());
+        assert_eq!(warped.parties, vec![principal(7), principal(8)]);
+        assert_eq!(
+            account(&discovery, 3).parties,
+            vec![principal(7), principal(8)]
+        );
+        assert!(account(&discovery, 2).parties.is_empty());
+    }
+
+    #[test]
+    fn credits_equal_to_a_fee_go_to_fee_wallets() {
+        let mut history = LedgerHistory::default();
+        issued(&mut history);
+        history.fee_amounts.insert((asset(1), U256::from(5u8)));
+        // 10 to account 2 and a fee of 5 to account 3
+        let events = vec![
+            update(&mut history, 1, 100, 85),
+            update(&mut history, 2, 0, 10),
+            update(&mut history, 3, 0, 5),
+        ];
+        history.transactions.push(tx(1, events));
+        // a transfer of exactly the fee amount, with no fee: its only credit is no fee
+        let events = vec![
+            update(&mut history, 1, 85, 80),
+            update(&mut history, 4, 0, 5),
+        ];
+        history.transactions.push(tx(2, events));
+        // two credits that both equal the fee cannot be told apart
+        let events = vec![
+            update(&mut history, 1, 80, 70),
+            update(&mut history, 5, 0, 5),
+            update(&mut history, 6, 0, 5),
+        ];
+        history.transactions.push(tx(3, events));
+        let discovery = attribute(history);
+
+        assert!(account(&discovery, 3).fee_wallet);
+        for account_id in [1, 2, 4, 5, 6] {
+            assert!(
+                !account(&discovery, account_id).fee_wallet,
+                "{}",
+                account_id
+            );
+        }
+    }
+
+    fn ledger_account(account_id: u8, balance: u32, parties: Vec<Principal>) -> LedgerAccount {
+        LedgerAccount {
+            account_id: Nat::from(account_id),
+            unique_asset_id: Some(asset(1)),
+            balance: U256::from(balance),
+            fee_wallet: false,
+            parties,
+        }
+    }
+
+    #[test]
+    fn distinct_balances_pair_holders_with_accounts() {
+        let held = vec![
+            (principal(1), U256::from(60u8)),
+            (principal(2), U256::from(40u8)),
+        ];
+        let accounts = [ledger_account(1, 40, vec![]), ledger_account(2, 60, vec![])];
+        let accounts = accounts.iter().collect::<Vec<_>>();
+
+        let mut pairs = pair_holders(&held, &accounts);
+        pairs.sort();
+        assert_eq!(pairs, vec![(0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn equal_balances_are_only_paired_by_their_transactions() {
+        let held = vec![
+            (principal(1), U256::from(50u8)),
+            (principal(2), U256::from(50u8)),
+        ];
+
+        // nothing tells the two accounts apart, so neither is paired
+        let accounts = [ledger_account(1, 50, vec![]), ledger_account(2, 50, vec![])];
+        let accounts = accounts.iter().collect::<Vec<_>>();
+        assert!(pair_holders(&held, &accounts).is_empty());
+
+        // each account's transactions name its holder
+        let accounts = [
+            ledger_account(1, 50, vec![principal(2)]),
+            ledger_account(2, 50, vec![principal(1)]),
+        ];
+        let accounts = accounts.iter().collect::<Vec<_>>();
+        let mut pairs = pair_holders(&held, &accounts);
+        pairs.sort();
+        assert_eq!(pairs, vec![(0, 1), (1, 0)]);
+
+        // once one account is named, the other holder is the only one left for the other account
+        let accounts = [
+            ledger_account(1, 50, vec![principal(2)]),
+            ledger_account(2, 50, vec![]),
+        ];
+        let accounts = accounts.iter().collect::<Vec<_>>();
+        let mut pairs = pair_holders(&held, &accounts);
+        pairs.sort();
+        assert_eq!(pairs, vec![(0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn a_holder_with_more_accounts_of_its_balance_stays_unpaired() {
+        let held = vec![(principal(1), U256::from(50u8))];
+        let accounts = [ledger_account(1, 50, vec![]), ledger_account(2, 50, vec![])];
+        let accounts = accounts.iter().collect::<Vec<_>>();
+        assert!(pair_holders(&held, &accounts).is_empty());
+    }
+}
@@ -0,0 +1,332 @@
+use ic_agent::{agent::RejectResponse, AgentError};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::{Cell, RefCell},
+    error::Error,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Inputs that make a scenario run reproducible: the seed of its random asset ids and the
+/// wall-clock time it starts at.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CassetteHeader {
+    pub seed: u64,
+    pub clock: String,
+}
+
+/// One canister call with its Candid bytes, hex-encoded.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub call_type: String,
+    pub method: String,
+    pub caller: String,
+    pub args: String,
+    pub response: Option<String>,
+    pub error: Option<String>,
+    /// Set when the error was a reject, so that replays fail with the same `AgentError`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reject: Option<RecordedReject>,
+}
+
+/// A reject of the replica or the canister, with its code and message.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedReject {
+    pub certified: bool,
+    #[serde(flatten)]
+    pub response: RejectResponse,
+}
+
+impl RecordedReject {
+    pub fn of(error: &AgentError) -> Option<Self> {
+        match error {
+            AgentError::CertifiedReject(response) => Some(Self {
+                certified: true,
+                response: response.clone(),
+            }),
+            AgentError::UncertifiedReject(response) => Some(Self {
+                certified: false,
+                response: response.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    fn to_error(&self) -> AgentError {
+        if self.certified {
+            AgentError::CertifiedReject(self.response.clone())
+        } else {
+            AgentError::UncertifiedReject(self.response.clone())
+        }
+    }
+}
+
+/// A cassette line after the header. Calls are appended as they complete, so `slot` restores
+/// the order they were issued in.
+#[derive(Serialize, Deserialize)]
+struct RecordedInteraction {
+    slot: usize,
+    #[serde(flatten)]
+    interaction: Interaction,
+}
+
+enum Mode {
+    Record { file: RefCell<File> },
+    Replay,
+}
+
+/// Recording of the canister calls of a session, stored as JSON lines: the header first, then
+/// one line per completed call. While recording, every call is appended as soon as it completes;
+/// while replaying, calls are answered from the recording in the order they were issued and any
+/// call that differs from the recorded one in call type, method, caller or arguments fails the
+/// replay.
+pub struct Cassette {
+    file_path: PathBuf,
+    mode: Mode,
+    header: CassetteHeader,
+    interactions: Vec<Option<Interaction>>,
+    /// Next slot to reserve when recording, next call to play when replaying
+    position: Cell<usize>,
+    /// First call of a replay that did not match the recording
+    mismatch: RefCell<Option<String>>,
+}
+
+impl Cassette {
+    /// Starts a new recording at `file_path`, replacing any previous one.
+    pub fn record<P: AsRef<Path>>(
+        file_path: P,
+        header: CassetteHeader,
+    ) -> Result<Self, Box<dyn Error>> {
+        let file_path = file_path.as_ref().to_path_buf();
+        let mut file = File::create(&file_path)?;
+        writeln!(file, "{}", serde_json::to_string(&header)?)?;
+
+        Ok(Self {
+            file_path,
+            mode: Mode::Record {
+                file: RefCell::new(file),
+            },
+            header,
+            interactions: Vec::new(),
+            position: Cell::new(0),
+            mismatch: RefCell::new(None),
+        })
+    }
+
+    pub fn replay<P: AsRef<Path>>(file_path: P) -> Result<Self, Box<dyn Error>> {
+        let file_path = file_path.as_ref().to_path_buf();
+        let content = fs::read_to_string(&file_path)?;
+        let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+        let header = serde_json::from_str(lines.next().ok_or("cassette is empty")?)?;
+
+        // a call that never completed leaves its slot empty
+        let mut interactions = Vec::new();
+        for line in lines {
+            let recorded: RecordedInteraction = serde_json::from_str(line)?;
+            if interactions.len() <= recorded.slot {
+                interactions.resize(recorded.slot + 1, None);
+            }
+            interactions[recorded.slot] = Some(recorded.interaction);
+        }
+
+        Ok(Self {
+            file_path,
+            mode: Mode::Replay,
+            header,
+            interactions,
+            position: Cell::new(0),
+            mismatch: RefCell::new(None),
+        })
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        matches!(self.mode, Mode::Replay)
+    }
+
+    pub fn header(&self) -> CassetteHeader {
+        self.header.clone()
+    }
+
+    /// Reserves the position of a call that is about to be sent, so that concurrent calls are
+    /// recorded in the order they were issued rather than the order they completed.
+    pub fn reserve(&self) -> usize {
+        let slot = self.position.get();
+        self.position.set(slot + 1);
+        slot
+    }
+
+    /// Appends a completed call to the recording.
+    pub fn store(&self, slot: usize, interaction: Interaction) -> Result<(), Box<dyn Error>> {
+        let Mode::Record { file } = &self.mode else {
+            return Err("cassette: cannot store calls while replaying".into());
+        };
+        let line = serde_json::to_string(&RecordedInteraction { slot, interaction })?;
+        let mut file = file.borrow_mut();
+        writeln!(file, "{}", line)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Answers a call from the recording; fails if the call was not expected at this point. The
+    /// first such call is kept and fails `finish` too, in case the caller tolerated the error.
+    pub fn play(
+        &self,
+        call_type: &str,
+        method: &str,
+        caller: &str,
+        args: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let position = self.position.get();
+        self.position.set(position + 1);
+
+        let Some(Some(recorded)) = self.interactions.get(position) else {
+            return Err(self.mismatch(format!(
+                "replay: unexpected call #{} {} {} beyond the end of {}",
+                position,
+                call_type,
+                method,
+                self.file_path.display()
+            )));
+        };
+
+        let args = hex::encode(args);
+        let actual = (call_type, method, caller, args.as_str());
+        let expected = (
+            recorded.call_type.as_str(),
+            recorded.method.as_str(),
+            recorded.caller.as_str(),
+            recorded.args.as_str(),
+        );
+        if actual != expected {
+            return Err(self.mismatch(format!(
+                "replay: call #{} does not match {} ({} {})\n  expected: {:?}\n  actual:   {:?}",
+                position,
+                self.file_path.display(),
+                call_type,
+                method,
+                expected,
+                actual
+            )));
+        }
+
+        match (&recorded.response, &recorded.reject, &recorded.error) {
+            (Some(response), _, _) => Ok(hex::decode(response)?),
+            (None, Some(reject), _) => Err(reject.to_error().into()),
+            (None, None, Some(error)) => Err(error.clone().into()),
+            (None, None, None) => Err(format!("replay: call #{} has no outcome", position).into()),
+        }
+    }
+
+    fn mismatch(&self, message: String) -> Box<dyn Error> {
+        self.mismatch
+            .borrow_mut()
+            .get_or_insert_with(|| message.clone());
+        message.into()
+    }
+
+    /// Fails a replay that did not make all recorded calls or made an unexpected one; a recording is already complete.
+    pub fn finish(&self) -> Result<(), Box<dyn Error>> {
+        match self.mode {
+            Mode::Record { .. } => Ok(()),
+            Mode::Replay => {
+                if let Some(mismatch) = self.mismatch.borrow().as_ref() {
+                    return Err(mismatch.clone().into());
+                }
+                let recorded = self.interactions.len();
+                let played = self.position.get();
+                if played < recorded {
+                    return Err(format!(
+                        "replay: only {} of {} recorded calls were made",
+                        played, recorded
+                    )
+                    .into());
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cassette at a temp path recording one `int_get_balance` query answered with `2a`.
+    fn recorded(name: &str) -> PathBuf {
+        let file_path =
+            std::env::temp_dir().join(format!("cassette-{}-{}.jsonl", name, std::process::id()));
+        let header = CassetteHeader {
+            seed: 7,
+            clock: "2026-01-01T00:00:00+00:00".to_string(),
+        };
+        let cassette = Cassette::record(&file_path, header).unwrap();
+        let slot = cassette.reserve();
+        let interaction = Interaction {
+            call_type: "query".to_string(),
+            method: "int_get_balance".to_string(),
+            caller: "2vxsx-fae".to_string(),
+            args: "01".to_string(),
+            response: Some("2a".to_string()),
+            error: None,
+            reject: None,
+        };
+        cassette.store(slot, interaction).unwrap();
+        file_path
+    }
+
+    #[test]
+    fn recorded_calls_are_played_back() {
+        let file_path = recorded("play");
+        let cassette = Cassette::replay(&file_path).unwrap();
+        fs::remove_file(&file_path).unwrap();
+
+        let response = cassette.play("query", "int_get_balance", "2vxsx-fae", &[1]);
+        assert_eq!(response.unwrap(), vec![0x2a]);
+        assert!(cassette.finish().is_ok());
+    }
+
+    #[test]
+    fn mismatching_calls_fail_the_call_and_the_replay() {
+        let file_path = recorded("mismatch");
+        let cassette = Cassette::replay(&file_path).unwrap();
+        fs::remove_file(&file_path).unwrap();
+
+        let error = cassette
+            .play("query", "int_get_balance", "2vxsx-fae", &[2])
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with(&format!(
+                "replay: call #0 does not match {} (query int_get_balance)",
+                file_path.display()
+            )),
+            "{}",
+            error
+        );
+        assert_eq!(cassette.finish().unwrap_err().to_string(), error);
+    }
+
+    #[test]
+    fn calls_beyond_the_recording_fail_the_call_and_the_replay() {
+        let file_path = recorded("beyond");
+        let cassette = Cassette::replay(&file_path).unwrap();
+        fs::remove_file(&file_path).unwrap();
+
+        cassette
+            .play("query", "int_get_balance", "2vxsx-fae", &[1])
+            .unwrap();
+        let error = cassette
+            .play("update", "int_transfer", "2vxsx-fae", &[])
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            format!(
+                "replay: unexpected call #1 update int_transfer beyond the end of {}",
+                file_path.display()
+            )
+        );
+        assert_eq!(cassette.finish().unwrap_err().to_string(), error);
+    }
+}
@@ -0,0 +1,578 @@
+use crate::{
+    batch::DEFAULT_MAX_IN_FLIGHT,
+    cache::{self, CacheTtl, ResponseCache},
+    logging::{LogFormat, LogLevel},
+    pacing::Pacing,
+    pricing::{self, FeeType},
+    read_policy::{self, ReadPolicy, CRITICAL_READS},
+    report::ReportFormat,
+    scenarios::CLMP_LEDGER_ID,
+};
+use clap::{Args, Parser, Subcommand};
+use std::{error::Error, net::SocketAddr, path::PathBuf, time::Duration};
+
+#[derive(Parser)]
+#[command(about = "CoreLedger canister demo-client")]
+pub struct Cli {
+    /// Run without waiting for ENTER between steps
+    #[arg(long)]
+    pub non_interactive: bool,
+
+    /// Delay between scenario steps, in milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 0)]
+    pub step_delay: u64,
+
+    /// Asset registry file mapping symbols to unique asset ids
+    #[arg(long, value_name = "FILE", default_value = "assets.json")]
+    pub registry: PathBuf,
+
+    /// Environment manifest written by `bootstrap`; without it the environment is detected
+    #[arg(long, value_name = "FILE", default_value = "environment.json")]
+    pub environment: PathBuf,
+
+    /// Cross-check balance and supply reads against certified update calls
+    #[arg(long)]
+    pub verified_reads: bool,
+
+    /// Read policy of a query method, e.g. int_get_tokens=verified; may be repeated
+    #[arg(long = "read-policy", value_name = "METHOD=POLICY", value_parser = read_policy::parse_read_policy)]
+    pub read_policies: Vec<(String, ReadPolicy)>,
+
+    /// Log level of canister calls (logs go to stderr)
+    #[arg(long, value_enum, default_value_t = LogLevel::Info)]
+    pub log_level: LogLevel,
+
+    /// Log output format
+    #[arg(long, value_enum, default_value_t = LogFormat::Compact)]
+    pub log_format: LogFormat,
+
+    /// Hash-chained log of every update call
+    #[arg(long, value_name = "FILE", default_value = "audit.jsonl")]
+    pub audit_log: PathBuf,
+
+    /// Record all canister calls of this run into a cassette file
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Answer all canister calls from a recorded cassette instead of the replica
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+
+    /// Cache rarely changing reads such as contract names and assets for this run
+    #[arg(long, conflicts_with_all = ["record", "replay"])]
+    pub cache: bool,
+
+    /// Cache rarely changing reads and keep them in this file between runs
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["record", "replay"])]
+    pub cache_file: Option<PathBuf>,
+
+    /// Cache time to live of a query method in seconds, `forever` or 0 to disable; may be repeated
+    #[arg(long = "cache-ttl", value_name = "METHOD=SECONDS", value_parser = cache::parse_cache_ttl)]
+    pub cache_ttls: Vec<(String, Option<CacheTtl>)>,
+
+    /// Serve Prometheus metrics of canister calls on this address, e.g. 127.0.0.1:9898
+    #[arg(long, value_name = "ADDR")]
+    pub metrics_addr: Option<SocketAddr>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the demo scenario (default)
+    Demo,
+    /// Show a balance matrix of arbitrary holders and assets
+    Balances(BalancesArgs),
+    /// Manage the local asset registry
+    #[command(subcommand)]
+    Assets(AssetsCommand),
+    /// Show a supply with its exchange rate in human-readable form
+    Supply(SupplyArgs),
+    /// Inspect the audit log of update calls
+    #[command(subcommand)]
+    Audit(AuditCommand),
+    /// Amend assets with legal documents and verify documents against the ledger
+    #[command(subcommand)]
+    Amendments(AmendmentsCommand),
+    /// Issue, transfer and inspect tokens, including individual tokens of bitwise assets
+    #[command(subcommand)]
+    Tokens(TokensCommand),
+    /// Set fees of assets and show current fees and their history
+    #[command(subcommand)]
+    Fees(FeesCommand),
+    /// Create and link the contracts of a CoreLedger environment and write its manifest
+    Bootstrap(BootstrapArgs),
+    /// Deploy controllers and wire them to assets and supplies
+    #[command(subcommand)]
+    Controllers(ControllersCommand),
+}
+
+#[derive(Args)]
+pub struct BootstrapArgs {
+    /// Identity alias creating the contracts and owning the controllers
+    #[arg(long)]
+    pub identity: String,
+
+    /// Id the CLMP ledger contract is registered under
+    #[arg(long, default_value_t = CLMP_LEDGER_ID)]
+    pub ledger_id: u16,
+
+    /// Decimal pointer of a new integration contract
+    #[arg(long, default_value = "1000000000")]
+    pub decimal_pointer: String,
+
+    /// File holding the deployment code of the integration contract, if it has to be created
+    #[arg(long, value_name = "FILE")]
+    pub integration_code: Option<PathBuf>,
+
+    /// File holding the deployment code of the CLMP contract, if it has to be created
+    #[arg(long, value_name = "FILE")]
+    pub clmp_code: Option<PathBuf>,
+
+    /// File holding the deployment code of controllers, if any have to be created
+    #[arg(long, value_name = "FILE")]
+    pub controller_code: Option<PathBuf>,
+
+    /// Number of controllers the environment should have
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub controllers: usize,
+}
+
+#[derive(Subcommand)]
+pub enum ControllersCommand {
+    /// Deploy a controller, assign it to an asset and supplies, validate it and seed its lists
+    Deploy(DeployControllerArgs),
+}
+
+#[derive(Args)]
+pub struct DeployControllerArgs {
+    /// Identity alias owning the controller (alice, bob, charlie, exchange)
+    #[arg(long)]
+    pub owner: String,
+
+    /// Identity alias assigning the controller to the asset and supplies; defaults to the owner
+    #[arg(long)]
+    pub issuer: Option<String>,
+
+    /// File holding the deployment code of the controller
+    #[arg(long, value_name = "FILE", required_unless_present = "copy_from")]
+    pub deployment_code: Option<PathBuf>,
+
+    /// Deploy with the deployment code of an existing controller contract
+    #[arg(long, value_name = "CONTRACT_ID", conflicts_with = "deployment_code")]
+    pub copy_from: Option<String>,
+
+    /// Asset symbol or unique asset id whose usage the controller governs
+    #[arg(long)]
+    pub asset: Option<String>,
+
+    /// Supply id to put under the controller; may be repeated
+    #[arg(long = "supply", value_name = "SUPPLY_ID")]
+    pub supplies: Vec<String>,
+
+    /// Address to blacklist, alias or principal, with an optional code; may be repeated
+    #[arg(long = "blacklist", value_name = "HOLDER[=CODE]")]
+    pub blacklist: Vec<String>,
+
+    /// Limit of an address, alias or principal, in the asset; may be repeated
+    #[arg(long = "limit", value_name = "HOLDER=AMOUNT", requires = "asset")]
+    pub limits: Vec<String>,
+}
+
+#[derive(Subcommand)]
+pub enum FeesCommand {
+    /// Set the fee of an asset through int_set_price
+    Set(SetFeeArgs),
+    /// Show the current fees, optionally of a single asset
+    List(ListFeesArgs),
+    /// Show every price change, optionally of a single asset
+    History(ListFeesArgs),
+}
+
+#[derive(Args)]
+pub struct SetFeeArgs {
+    /// Asset symbol or unique asset id
+    #[arg(long)]
+    pub asset: String,
+
+    /// Fee type: transfer, warp or a raw code
+    #[arg(long, value_name = "TYPE", value_parser = pricing::parse_fee_type)]
+    pub fee_type: FeeType,
+
+    /// Fee amount, in the asset
+    #[arg(long)]
+    pub amount: String,
+
+    /// Wallet receiving the fees, alias or principal
+    #[arg(long)]
+    pub wallet: String,
+
+    /// Identity alias setting the price (alice, bob, charlie, exchange)
+    #[arg(long)]
+    pub identity: String,
+}
+
+#[derive(Args)]
+pub struct ListFeesArgs {
+    /// Asset symbol or unique asset id
+    #[arg(long)]
+    pub asset: Option<String>,
+
+    /// Maximum number of event queries in flight at a time
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_IN_FLIGHT)]
+    pub max_in_flight: usize,
+}
+
+#[derive(Subcommand)]
+pub enum TokensCommand {
+    /// Issue tokens to the issuer of an asset
+    Issue(IssueTokensArgs),
+    /// Transfer tokens to another holder
+    Transfer(TransferTokensArgs),
+    /// Show the tokens held by a holder, one position per line for bitwise assets
+    Held(HeldTokensArgs),
+    /// Show issued, destroyed and outstanding tokens of assets and check them against events
+    Supply(TokenSupplyArgs),
+    /// List the holders of an asset with their balances and shares
+    Holders(HoldersArgs),
+}
+
+#[derive(Args)]
+pub struct HoldersArgs {
+    /// Asset symbol or unique asset id
+    #[arg(long)]
+    pub asset: String,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+
+    /// Write the cap table to a file instead of printing it
+    #[arg(long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Maximum number of queries in flight at a time
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_IN_FLIGHT)]
+    pub max_in_flight: usize,
+}
+
+#[derive(Args)]
+pub struct TokenSupplyArgs {
+    /// Asset symbol or unique asset id; may be repeated
+    #[arg(long = "asset", value_name = "ASSET", required = true)]
+    pub assets: Vec<String>,
+
+    /// Also show every issue and destroy event
+    #[arg(long)]
+    pub timeline: bool,
+
+    /// Maximum number of event queries in flight at a time
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_IN_FLIGHT)]
+    pub max_in_flight: usize,
+}
+
+#[derive(Args)]
+pub struct IssueTokensArgs {
+    /// Asset symbol or unique asset id
+    #[arg(long)]
+    pub asset: String,
+
+    /// Identity alias of the issuer (alice, bob, charlie, exchange)
+    #[arg(long)]
+    pub issuer: String,
+
+    /// Amount, or token positions such as 0-3,7 for bitwise assets
+    #[arg(long)]
+    pub amount: String,
+}
+
+#[derive(Args)]
+pub struct TransferTokensArgs {
+    /// Asset symbol or unique asset id
+    #[arg(long)]
+    pub asset: String,
+
+    /// Identity alias of the sender (alice, bob, charlie, exchange)
+    #[arg(long)]
+    pub from: String,
+
+    /// Receiver alias or principal
+    #[arg(long)]
+    pub to: String,
+
+    /// Amount, or token positions such as 0-3,7 for bitwise assets
+    #[arg(long)]
+    pub amount: String,
+
+    /// Only show the amount, the fees of the asset and the total, without transferring
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct HeldTokensArgs {
+    /// Asset symbol or unique asset id
+    #[arg(long)]
+    pub asset: String,
+
+    /// Holder alias or principal
+    #[arg(long)]
+    pub holder: String,
+}
+
+#[derive(Subcommand)]
+pub enum AmendmentsCommand {
+    /// Hash a document and submit it as an amendment of an asset
+    Create(CreateAmendmentArgs),
+    /// Check a local document against the hash of an amendment on the ledger
+    Verify(VerifyAmendmentArgs),
+    /// List the amendments of a ledger or of a single asset
+    History(AmendmentHistoryArgs),
+}
+
+#[derive(Args)]
+pub struct CreateAmendmentArgs {
+    /// Asset symbol or unique asset id
+    #[arg(long)]
+    pub asset: String,
+
+    /// Legal document whose SHA-256 hash is recorded on the ledger
+    #[arg(long, value_name = "FILE")]
+    pub document: PathBuf,
+
+    /// Identity alias of the asset issuer (alice, bob, charlie, exchange)
+    #[arg(long)]
+    pub issuer: String,
+
+    /// Transfer the asset to a new issuer (alias or principal) with this amendment
+    #[arg(long, value_name = "ISSUER")]
+    pub new_issuer: Option<String>,
+}
+
+#[derive(Args)]
+pub struct VerifyAmendmentArgs {
+    /// Amendment id
+    pub amendment_id: String,
+
+    /// Local copy of the legal document
+    #[arg(long, value_name = "FILE")]
+    pub document: PathBuf,
+}
+
+#[derive(Args)]
+pub struct AmendmentHistoryArgs {
+    /// Only list amendments of this asset symbol or unique asset id
+    #[arg(long)]
+    pub asset: Option<String>,
+
+    /// Id of the ledger to list when no asset is given
+    #[arg(long, default_value_t = 1)]
+    pub ledger_id: u16,
+
+    /// Maximum number of queries in flight at a time
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_IN_FLIGHT)]
+    pub max_in_flight: usize,
+}
+
+#[derive(Subcommand)]
+pub enum AuditCommand {
+    /// Check the hash chain and cross-reference recorded tx ids with get_tx
+    Verify(AuditVerifyArgs),
+}
+
+#[derive(Args)]
+pub struct AuditVerifyArgs {
+    /// Only check the hash chain, without querying the ledger
+    #[arg(long)]
+    pub offline: bool,
+}
+
+#[derive(Subcommand)]
+pub enum AssetsCommand {
+    /// List registered assets
+    List,
+    /// Register assets created on the ledger that are missing from the registry
+    Sync,
+    /// Register an asset under a symbol
+    Register(RegisterAssetArgs),
+    /// Create an asset anchored to the hash of a document file or directory
+    Create(CreateAssetArgs),
+    /// Check a local document against the hash anchored in an asset
+    Verify(VerifyAssetArgs),
+    /// Reserve a new asset id on the ledger without activating it
+    Reserve(ReserveAssetArgs),
+    /// Activate a reserved asset id, resuming interrupted reservations
+    Activate(ActivateAssetArgs),
+    /// List reserved asset ids that are not activated yet
+    Pending,
+}
+
+#[derive(Args)]
+pub struct ReserveAssetArgs {
+    /// Symbol to register the asset under once it is activated
+    #[arg(long)]
+    pub symbol: String,
+
+    /// Identity alias of the issuer (alice, bob, charlie, exchange)
+    #[arg(long)]
+    pub issuer: String,
+
+    /// Number of decimals used to display amounts
+    #[arg(long, default_value_t = 0)]
+    pub decimals: u8,
+
+    /// Id of the ledger to reserve the asset id on
+    #[arg(long, default_value_t = 1)]
+    pub ledger_id: u16,
+}
+
+#[derive(Args)]
+pub struct ActivateAssetArgs {
+    /// Symbol or unique asset id of the reservation
+    pub asset: String,
+
+    /// Document file, or directory of documents, whose hash is anchored in the asset
+    #[arg(long, value_name = "PATH")]
+    pub document: PathBuf,
+
+    /// Create a bitwise asset
+    #[arg(long)]
+    pub bitwise: bool,
+
+    /// Contract id of the controller of the asset
+    #[arg(long, value_name = "CONTRACT_ID")]
+    pub controller: Option<String>,
+
+    /// Identity alias to activate with, instead of the one that reserved the id
+    #[arg(long)]
+    pub issuer: Option<String>,
+}
+
+#[derive(Args)]
+pub struct CreateAssetArgs {
+    /// Symbol to register the new asset under
+    #[arg(long)]
+    pub symbol: String,
+
+    /// Document file, or directory of documents, whose hash is anchored in the asset
+    #[arg(long, value_name = "PATH")]
+    pub document: PathBuf,
+
+    /// Identity alias of the issuer (alice, bob, charlie, exchange)
+    #[arg(long)]
+    pub issuer: String,
+
+    /// Number of decimals used to display amounts
+    #[arg(long, default_value_t = 0)]
+    pub decimals: u8,
+
+    /// Create a bitwise asset, whose tokens are individual bit positions
+    #[arg(long)]
+    pub bitwise: bool,
+
+    /// Id of the ledger to create the asset on
+    #[arg(long, default_value_t = 1)]
+    pub ledger_id: u16,
+}
+
+#[derive(Args)]
+pub struct VerifyAssetArgs {
+    /// Asset symbol or unique asset id
+    pub asset: String,
+
+    /// Local copy of the anchored document file or directory
+    #[arg(long, value_name = "PATH")]
+    pub document: PathBuf,
+}
+
+#[derive(Args)]
+pub struct RegisterAssetArgs {
+    /// Symbol to show instead of the unique asset id, e.g. USD
+    #[arg(long)]
+    pub symbol: String,
+
+    /// Asset id on the ledger, decimal or 0x-prefixed hex
+    #[arg(long = "asset-id", value_name = "ASSET_ID")]
+    pub asset_id: String,
+
+    /// Number of decimals used to display amounts
+    #[arg(long, default_value_t = 0)]
+    pub decimals: u8,
+
+    /// Id of the ledger the asset lives on
+    #[arg(long, default_value_t = 1)]
+    pub ledger_id: u16,
+}
+
+#[derive(Args)]
+pub struct BalancesArgs {
+    /// Holder alias (alice, bob, charlie, exchange) or principal; may be repeated
+    #[arg(long = "holder", value_name = "HOLDER", required = true)]
+    pub holders: Vec<String>,
+
+    /// Asset symbol or unique asset id (decimal or 0x-prefixed hex); may be repeated
+    #[arg(long = "asset", value_name = "ASSET", required = true)]
+    pub assets: Vec<String>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+
+    /// Save the fetched balances as a JSON snapshot
+    #[arg(long, value_name = "FILE")]
+    pub save: Option<PathBuf>,
+
+    /// Compare the balances with a previously saved snapshot
+    #[arg(long, value_name = "FILE")]
+    pub diff: Option<PathBuf>,
+
+    /// Maximum number of balance queries in flight at a time
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_IN_FLIGHT)]
+    pub max_in_flight: usize,
+}
+
+#[derive(Args)]
+pub struct SupplyArgs {
+    /// Supply id
+    pub supply_id: String,
+
+    /// Offered asset symbol or id, used to label the exchange rate
+    #[arg(long)]
+    pub offered: Option<String>,
+
+    /// Desired asset symbol or id, used to label the exchange rate
+    #[arg(long)]
+    pub desired: Option<String>,
+}
+
+impl Cli {
+    pub fn pacing(&self) -> Pacing {
+        Pacing::new(
+            !self.non_interactive,
+            Duration::from_millis(self.step_delay),
+        )
+    }
+
+    /// Read policies requested on the command line; `--read-policy` overrides `--verified-reads`.
+    pub fn read_policies(&self) -> Vec<(String, ReadPolicy)> {
+        let critical = CRITICAL_READS
+            .iter()
+            .filter(|_| self.verified_reads)
+            .map(|method_name| (method_name.to_string(), ReadPolicy::Verified));
+        critical.chain(self.read_policies.clone()).collect()
+    }
+
+    /// Response cache requested on the command line, if any.
+    pub fn response_cache(&self) -> Result<Option<ResponseCache>, Box<dyn Error>> {
+        let mut cache = match &self.cache_file {
+            Some(file_path) => ResponseCache::open(file_path)?,
+            None if self.cache => ResponseCache::default(),
+            None => return Ok(None),
+        };
+        for (method_name, ttl) in &self.cache_ttls {
+            cache.set_ttl(method_name, *ttl);
+        }
+        Ok(Some(cache))
+    }
+}
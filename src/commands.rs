@@ -0,0 +1,1163 @@
+use crate::{
+    amendment,
+    asset_creation::{self, CreateAssetRequest, CreationStep},
+    audit::{self, AuditLog},
+    batch::DEFAULT_MAX_IN_FLIGHT,
+    cap_table::{self, CapTable},
+    cli::{
+        ActivateAssetArgs, AmendmentHistoryArgs, AmendmentsCommand, AssetsCommand, AuditCommand,
+        BalancesArgs, BootstrapArgs, ControllersCommand, CreateAmendmentArgs, CreateAssetArgs,
+        FeesCommand, HeldTokensArgs, HoldersArgs, IssueTokensArgs, ListFeesArgs, RegisterAssetArgs,
+        ReserveAssetArgs, SetFeeArgs, SupplyArgs, TokenSupplyArgs, TokensCommand,
+        TransferTokensArgs, VerifyAmendmentArgs, VerifyAssetArgs,
+    },
+    controller::{self, ControllerPlan},
+    document,
+    environment::{self, BootstrapPlan, Environment},
+    exchange_rate::ExchangeRate,
+    pricing::{self, FeeSchedule},
+    registry::{AssetRegistry, ReservedAsset},
+    report::{self, BalanceReport, BalanceSnapshot, ReportAsset, ReportHolder},
+    scenarios::CLMP_LEDGER_ID,
+    token_supply, ContractId, LedgerId, Service, UniqueAssetId, User, U256,
+};
+use candid::{Nat, Principal};
+use num_bigint::BigUint;
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+pub async fn balances(
+    service: &Service,
+    registry: &AssetRegistry,
+    args: &BalancesArgs,
+) -> Result<(), Box<dyn Error>> {
+    let holders = args
+        .holders
+        .iter()
+        .map(|h| parse_holder(h))
+        .collect::<Result<Vec<_>, _>>()?;
+    let assets = args
+        .assets
+        .iter()
+        .map(|a| {
+            let unique_asset_id = registry.resolve(a)?;
+            Ok(ReportAsset {
+                label: registry.label(&unique_asset_id),
+                unique_asset_id,
+                decimals: registry.decimals(&unique_asset_id),
+                bitwise: registry.is_bitwise(&unique_asset_id),
+            })
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+    let mut report = BalanceReport::fetch(service, holders, assets, args.max_in_flight).await?;
+    if let Some(previous) = &args.diff {
+        report = report.with_previous(&BalanceSnapshot::load(previous)?)?;
+    }
+
+    println!("{}", report.render(args.format)?);
+
+    if let Some(file_path) = &args.save {
+        report.snapshot().save(file_path)?;
+    }
+
+    Ok(())
+}
+
+pub async fn assets(
+    service: &Service,
+    registry: &AssetRegistry,
+    environment_path: Option<&Path>,
+    command: &AssetsCommand,
+) -> Result<bool, Box<dyn Error>> {
+    match command {
+        AssetsCommand::List => {
+            list_assets(registry)?;
+            Ok(true)
+        }
+        AssetsCommand::Sync => {
+            let environment =
+                Environment::resolve(service, environment_path, CLMP_LEDGER_ID).await?;
+            let registered = registry
+                .sync(
+                    service,
+                    environment.ledger_id,
+                    environment.ledger_contract_id()?,
+                )
+                .await?;
+            registry.save()?;
+
+            println!("{} new asset(s) registered", registered);
+            list_assets(registry)?;
+            Ok(true)
+        }
+        AssetsCommand::Register(args) => {
+            register_asset(registry, args)?;
+            Ok(true)
+        }
+        AssetsCommand::Create(args) => create_asset(service, registry, args).await,
+        AssetsCommand::Verify(args) => verify_asset(service, registry, args).await,
+        AssetsCommand::Reserve(args) => {
+            reserve_asset(service, registry, args).await?;
+            Ok(true)
+        }
+        AssetsCommand::Activate(args) => {
+            activate_asset(service, registry, args).await?;
+            Ok(true)
+        }
+        AssetsCommand::Pending => {
+            list_reservations(service, registry).await?;
+            Ok(true)
+        }
+    }
+}
+
+async fn reserve_asset(
+    service: &Service,
+    registry: &AssetRegistry,
+    args: &ReserveAssetArgs,
+) -> Result<(), Box<dyn Error>> {
+    let contract_id = ledger_contract_id(service, args.ledger_id).await?;
+    let issuer = User::resolve_alias(&args.issuer)?;
+    let asset_id =
+        asset_creation::unused_asset_id(service, registry, &contract_id, args.ledger_id, || {
+            asset_creation::random_asset_id(&mut rand::thread_rng())
+        })
+        .await?;
+    let unique_asset_id = UniqueAssetId::compose(&asset_id, args.ledger_id)?;
+
+    // saved before the call, so that an interrupted reservation can be resumed by activation
+    registry.add_reservation(ReservedAsset {
+        symbol: args.symbol.clone(),
+        decimals: args.decimals,
+        unique_asset_id,
+        issuer: args.issuer.clone(),
+        reserved_at: chrono::Utc::now().to_rfc3339(),
+    });
+    registry.save()?;
+
+    service.set_identity(issuer.identity);
+    service
+        .led_base_create_asset(&contract_id, &asset_id)
+        .await?;
+
+    println!("{} reserved: {}", args.symbol, unique_asset_id);
+    println!(
+        "Activate with: assets activate {} --document <PATH>",
+        args.symbol
+    );
+    Ok(())
+}
+
+async fn activate_asset(
+    service: &Service,
+    registry: &AssetRegistry,
+    args: &ActivateAssetArgs,
+) -> Result<(), Box<dyn Error>> {
+    let reservation = registry
+        .reservation(&args.asset)
+        .ok_or_else(|| format!("no reservation for {}, see `assets pending`", args.asset))?;
+    let (asset_id, ledger_id) = reservation.unique_asset_id.decompose();
+    let contract_id = ledger_contract_id(service, ledger_id).await?;
+    let issuer_alias = args.issuer.as_ref().unwrap_or(&reservation.issuer);
+    let issuer = User::resolve_alias(issuer_alias)?;
+    service.set_identity(issuer.identity);
+
+    let state = asset_creation::asset_state(service, &contract_id, &asset_id).await?;
+    let steps = state.remaining_steps();
+    if steps.contains(&CreationStep::Reserve) {
+        // the reservation was interrupted before it reached the ledger
+        println!("Reserving {} again", reservation.unique_asset_id);
+        service
+            .led_base_create_asset(&contract_id, &asset_id)
+            .await?;
+    }
+    if !steps.contains(&CreationStep::Activate) {
+        println!("{} is already active", reservation.unique_asset_id);
+    } else {
+        let document = document::hash_document(&args.document)?;
+        if let Some(manifest) = &document.manifest {
+            print!("Manifest of {}:\n{}", args.document.display(), manifest);
+        }
+        let controller = args
+            .controller
+            .as_deref()
+            .map(|c| c.parse::<Nat>())
+            .transpose()?;
+        service
+            .led_base_activate_asset(
+                &contract_id,
+                &asset_id,
+                &document.hash,
+                &args.bitwise,
+                &controller,
+            )
+            .await?;
+        println!("Document hash: {:#x}", document.hash.0);
+    }
+
+    registry.register(
+        &reservation.symbol,
+        reservation.decimals,
+        &asset_id,
+        ledger_id,
+    )?;
+    if let Some(asset) = service.led_base_get_asset(&contract_id, &asset_id).await? {
+        registry.set_bitwise(&reservation.unique_asset_id, asset.bitwise);
+    }
+    registry.remove_reservation(&reservation.unique_asset_id);
+    registry.save()?;
+
+    println!("{}: {}", reservation.symbol, reservation.unique_asset_id);
+    Ok(())
+}
+
+async fn list_reservations(
+    service: &Service,
+    registry: &AssetRegistry,
+) -> Result<(), Box<dyn Error>> {
+    use prettytable::{Cell, Row, Table};
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Symbol"),
+        Cell::new("Issuer"),
+        Cell::new("Reserved at"),
+        Cell::new("Unique asset id"),
+        Cell::new("Ledger state"),
+    ]));
+    for reservation in registry.reservations() {
+        let (asset_id, ledger_id) = reservation.unique_asset_id.decompose();
+        let contract_id = ledger_contract_id(service, ledger_id).await?;
+        let state = asset_creation::asset_state(service, &contract_id, &asset_id).await?;
+        table.add_row(Row::new(vec![
+            Cell::new(&reservation.symbol),
+            Cell::new(&reservation.issuer),
+            Cell::new(&reservation.reserved_at),
+            Cell::new(&reservation.unique_asset_id.to_hex()),
+            Cell::new(state.as_str()),
+        ]));
+    }
+    table.printstd();
+
+    Ok(())
+}
+
+async fn create_asset(
+    service: &Service,
+    registry: &AssetRegistry,
+    args: &CreateAssetArgs,
+) -> Result<bool, Box<dyn Error>> {
+    let contract_id = ledger_contract_id(service, args.ledger_id).await?;
+    let issuer = User::resolve_alias(&args.issuer)?;
+    let document = document::hash_document(&args.document)?;
+    if let Some(manifest) = &document.manifest {
+        print!("Manifest of {}:\n{}", args.document.display(), manifest);
+    }
+
+    let asset_id =
+        asset_creation::unused_asset_id(service, registry, &contract_id, args.ledger_id, || {
+            asset_creation::random_asset_id(&mut rand::thread_rng())
+        })
+        .await?;
+    let request = CreateAssetRequest {
+        asset_hash: document.hash,
+        asset_bitwise: args.bitwise,
+        ..CreateAssetRequest::new(asset_id)
+    };
+    service.set_identity(issuer.identity);
+    service
+        .led_base_activate_asset(
+            &contract_id,
+            &request.asset_id,
+            &request.asset_hash,
+            &request.asset_bitwise,
+            &request.asset_controller,
+        )
+        .await?;
+
+    let unique_asset_id = registry.register(
+        &args.symbol,
+        args.decimals,
+        &request.asset_id,
+        args.ledger_id,
+    )?;
+    registry.set_bitwise(&unique_asset_id, args.bitwise);
+    registry.save()?;
+
+    println!("Document hash: {:#x}", request.asset_hash.0);
+    println!("{}: {}", args.symbol, unique_asset_id);
+    Ok(true)
+}
+
+async fn verify_asset(
+    service: &Service,
+    registry: &AssetRegistry,
+    args: &VerifyAssetArgs,
+) -> Result<bool, Box<dyn Error>> {
+    let unique_asset_id = registry.resolve(&args.asset)?;
+    let (asset_id, ledger_id) = unique_asset_id.decompose();
+    let contract_id = ledger_contract_id(service, ledger_id).await?;
+    let asset = service
+        .led_base_get_asset(&contract_id, &asset_id)
+        .await?
+        .ok_or_else(|| format!("asset {} not found", unique_asset_id))?;
+    let local = document::hash_document(&args.document)?.hash;
+
+    let label = registry.label(&unique_asset_id);
+    let created_on = asset
+        .created_on
+        .map(|t| chrono::DateTime::from_timestamp_nanos(t as i64).to_rfc3339())
+        .unwrap_or_else(|| "not activated".to_string());
+    println!("Asset:                {} ({})", label, unique_asset_id);
+    println!("Issuer:               {}", asset.issuer);
+    println!("Created on:           {}", created_on);
+    println!("Local document hash:  {:#x}", local.0);
+
+    let Some(hash) = asset.hash else {
+        println!("[FAIL] {} has no anchored hash", label);
+        return Ok(false);
+    };
+    let anchored = document::hash_from_bytes(&hash);
+    println!("Anchored hash:        {:#x}", anchored.0);
+
+    let matches = local == anchored;
+    if matches {
+        println!("[OK] {} matches {}", args.document.display(), label);
+    } else {
+        println!(
+            "[FAIL] {} does not match {}",
+            args.document.display(),
+            label
+        );
+    }
+    Ok(matches)
+}
+
+pub async fn supply(
+    service: &Service,
+    registry: &AssetRegistry,
+    args: &SupplyArgs,
+) -> Result<(), Box<dyn Error>> {
+    let supply_id = args.supply_id.parse::<Nat>()?;
+    let supply = service
+        .int_get_supply(&supply_id)
+        .await?
+        .ok_or_else(|| format!("supply {} not found", supply_id))?;
+    let decimal_ptr = service.int_get_decimal_ptr().await?;
+
+    // the supply does not name its assets, so they are labelled from the command line
+    let asset = |arg: &Option<String>, fallback: &str| -> Result<(String, u8), Box<dyn Error>> {
+        match arg {
+            Some(text) => {
+                let unique_asset_id = registry.resolve(text)?;
+                Ok((
+                    registry.label(&unique_asset_id),
+                    registry.decimals(&unique_asset_id),
+                ))
+            }
+            None => Ok((fallback.to_string(), 0)),
+        }
+    };
+    let (offered_label, offered_decimals) = asset(&args.offered, "offered")?;
+    let (desired_label, desired_decimals) = asset(&args.desired, "desired")?;
+
+    let rate = ExchangeRate::from_raw(supply.exchange_rate.clone(), decimal_ptr);
+    let valid_until = chrono::DateTime::from_timestamp_nanos(supply.valid_until as i64);
+
+    println!("Supply:        {}", supply_id);
+    println!("Owner:         {}", supply.owner);
+    println!(
+        "Open amount:   {} {}",
+        supply.open_amount.format_units(offered_decimals),
+        offered_label
+    );
+    println!("Valid until:   {}", valid_until.to_rfc3339());
+    println!("Exchange rate: {} (raw)", supply.exchange_rate);
+    println!(
+        "               {}",
+        rate.describe(
+            (&offered_label, offered_decimals),
+            (&desired_label, desired_decimals)
+        )
+    );
+    // a zero raw rate has no inverse
+    let counter_rate = rate
+        .inverse()
+        .map_or_else(|_| "n/a".to_string(), |inverse| inverse.raw().to_string());
+    println!(
+        "Counter rate:  {} (raw rate of a {} => {} supply at the same price)",
+        counter_rate, desired_label, offered_label
+    );
+
+    Ok(())
+}
+
+/// Returns whether the audit log is intact and every recorded tx id exists on the ledger.
+pub async fn audit(
+    service: &Service,
+    audit_log_path: &Path,
+    command: &AuditCommand,
+) -> Result<bool, Box<dyn Error>> {
+    use prettytable::{Cell, Row, Table};
+
+    let AuditCommand::Verify(args) = command;
+    let lines = AuditLog::read(audit_log_path)?;
+    let problems = audit::verify_chain(&lines);
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Seq"),
+        Cell::new("Timestamp"),
+        Cell::new("Method"),
+        Cell::new("Caller"),
+        Cell::new("Outcome"),
+        Cell::new("Tx id"),
+        Cell::new("Ledger"),
+    ]));
+
+    let mut missing = 0;
+    for line in &lines {
+        let Ok(entry) = &line.entry else {
+            continue;
+        };
+        let record = &entry.record;
+        let ledger = match (&record.tx_id, args.offline) {
+            (Some(_), true) => "not checked".to_string(),
+            (Some(tx_id), false) => match service.get_tx(&tx_id.parse::<Nat>()?).await? {
+                Some(tx) => format!("{} event(s)", tx.events.len()),
+                None => {
+                    missing += 1;
+                    "MISSING".to_string()
+                }
+            },
+            (None, _) => String::new(),
+        };
+        table.add_row(Row::new(vec![
+            Cell::new(&record.seq.to_string()),
+            Cell::new(&record.timestamp),
+            Cell::new(&record.method),
+            Cell::new(&record.caller),
+            Cell::new(&record.outcome),
+            Cell::new(record.tx_id.as_deref().unwrap_or("")),
+            Cell::new(&ledger),
+        ]));
+    }
+    table.printstd();
+
+    for (line_number, problem) in &problems {
+        println!("[FAIL] line {}: {}", line_number, problem);
+    }
+    // truncation of the tail is not detectable from the log itself, compare with a kept copy
+    if let Some(hash) = audit::head_hash(&lines) {
+        println!("head: {}", hash);
+    }
+    println!(
+        "{} entries, {} chain problem(s), {} tx id(s) missing from the ledger",
+        lines.len(),
+        problems.len(),
+        missing
+    );
+
+    Ok(problems.is_empty() && missing == 0)
+}
+
+pub async fn amendments(
+    service: &Service,
+    registry: &AssetRegistry,
+    command: &AmendmentsCommand,
+) -> Result<bool, Box<dyn Error>> {
+    match command {
+        AmendmentsCommand::Create(args) => {
+            create_amendment(service, registry, args).await?;
+            Ok(true)
+        }
+        AmendmentsCommand::Verify(args) => verify_amendment(service, args).await,
+        AmendmentsCommand::History(args) => {
+            amendment_history(service, registry, args).await?;
+            Ok(true)
+        }
+    }
+}
+
+async fn create_amendment(
+    service: &Service,
+    registry: &AssetRegistry,
+    args: &CreateAmendmentArgs,
+) -> Result<(), Box<dyn Error>> {
+    let (asset_id, ledger_id) = registry.resolve(&args.asset)?.decompose();
+    let contract_id = ledger_contract_id(service, ledger_id).await?;
+    let issuer = User::resolve_alias(&args.issuer)?;
+    let document = document::hash_document(&args.document)?;
+    if let Some(manifest) = &document.manifest {
+        print!("Manifest of {}:\n{}", args.document.display(), manifest);
+    }
+    let hash = document.hash;
+
+    service.set_identity(issuer.identity);
+    let response = match &args.new_issuer {
+        Some(new_issuer) => {
+            let new_issuer = parse_holder(new_issuer)?.principal;
+            service
+                .led_amen_change_issuer(&contract_id, &asset_id, &hash, &new_issuer)
+                .await?
+        }
+        None => {
+            service
+                .led_amen_create_amendment(&contract_id, &asset_id, &hash)
+                .await?
+        }
+    };
+    service.invalidate_asset(&contract_id, &asset_id)?;
+
+    println!("Document hash: {:#x}", hash.0);
+    println!(
+        "Amendment {} created (tx id {})",
+        response.data, response.tx_id
+    );
+    Ok(())
+}
+
+async fn verify_amendment(
+    service: &Service,
+    args: &VerifyAmendmentArgs,
+) -> Result<bool, Box<dyn Error>> {
+    let amendment_id = args.amendment_id.parse::<Nat>()?;
+    let amendment = service
+        .led_amen_get_amendment(&amendment_id)
+        .await?
+        .ok_or_else(|| format!("amendment {} not found", amendment_id))?;
+    let local = document::hash_document(&args.document)?.hash;
+    let on_ledger = document::hash_from_bytes(&amendment.hash);
+
+    println!("Local document hash:  {:#x}", local.0);
+    println!("Amendment hash:       {:#x}", on_ledger.0);
+    let matches = local == on_ledger;
+    if matches {
+        println!(
+            "[OK] {} matches amendment {}",
+            args.document.display(),
+            amendment_id
+        );
+    } else {
+        println!(
+            "[FAIL] {} does not match amendment {}",
+            args.document.display(),
+            amendment_id
+        );
+    }
+    Ok(matches)
+}
+
+async fn amendment_history(
+    service: &Service,
+    registry: &AssetRegistry,
+    args: &AmendmentHistoryArgs,
+) -> Result<(), Box<dyn Error>> {
+    use prettytable::{Cell, Row, Table};
+
+    let (asset_id, ledger_id) = match &args.asset {
+        Some(asset) => {
+            let (asset_id, ledger_id) = registry.resolve(asset)?.decompose();
+            (Some(asset_id), ledger_id)
+        }
+        None => (None, args.ledger_id),
+    };
+    let contract_id = ledger_contract_id(service, ledger_id).await?;
+    let records = amendment::history(
+        service,
+        ledger_id,
+        &contract_id,
+        asset_id.as_ref(),
+        args.max_in_flight,
+    )
+    .await?;
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Amendment"),
+        Cell::new("Asset"),
+        Cell::new("Created on"),
+        Cell::new("Document hash"),
+    ]));
+    for record in &records {
+        let asset = match &record.asset_id {
+            Some(asset_id) => match UniqueAssetId::compose(asset_id, ledger_id) {
+                Ok(unique_asset_id) => registry.label(&unique_asset_id),
+                Err(_) => asset_id.to_string(),
+            },
+            None => "unknown asset".to_string(),
+        };
+        let created_on = chrono::DateTime::from_timestamp_nanos(record.created_on as i64);
+        table.add_row(Row::new(vec![
+            Cell::new(&record.amendment_id.to_string()),
+            Cell::new(&asset),
+            Cell::new(&created_on.to_rfc3339()),
+            Cell::new(&format!("{:#x}", record.hash.0)),
+        ]));
+    }
+    table.printstd();
+    println!("{} amendment(s)", records.len());
+
+    Ok(())
+}
+
+pub async fn tokens(
+    service: &Service,
+    registry: &AssetRegistry,
+    command: &TokensCommand,
+) -> Result<bool, Box<dyn Error>> {
+    match command {
+        TokensCommand::Issue(args) => issue_tokens(service, registry, args).await?,
+        TokensCommand::Transfer(args) => transfer_tokens(service, registry, args).await?,
+        TokensCommand::Held(args) => held_tokens(service, registry, args).await?,
+        TokensCommand::Supply(args) => return token_supply(service, registry, args).await,
+        TokensCommand::Holders(args) => holders(service, registry, args).await?,
+    }
+    Ok(true)
+}
+
+async fn holders(
+    service: &Service,
+    registry: &AssetRegistry,
+    args: &HoldersArgs,
+) -> Result<(), Box<dyn Error>> {
+    let unique_asset_id = registry.resolve(&args.asset)?;
+    let discovery = cap_table::discover_accounts(service, args.max_in_flight).await?;
+
+    let aliases = User::ALIASES
+        .into_iter()
+        .filter_map(|alias| Some((User::from_alias(alias)?.principal, alias)))
+        .collect::<Vec<_>>();
+    let candidates = aliases.iter().map(|(p, _)| *p).collect::<Vec<_>>();
+    let holder_label = |principal: &Principal| {
+        aliases
+            .iter()
+            .find(|(p, _)| p == principal)
+            .map_or_else(|| principal.to_text(), |(_, alias)| alias.to_string())
+    };
+    let table = CapTable::fetch(
+        service,
+        registry,
+        &unique_asset_id,
+        &discovery,
+        &candidates,
+        holder_label,
+        args.max_in_flight,
+    )
+    .await?;
+
+    let rendered = table.render(args.format)?;
+    match &args.output {
+        Some(file_path) => {
+            std::fs::write(file_path, rendered)?;
+            println!(
+                "Wrote {} holder(s) and {} unresolved account(s) to {}",
+                table.rows.len(),
+                table.unresolved.len(),
+                file_path.display()
+            );
+        }
+        None => println!("{}", rendered),
+    }
+
+    let unknown = discovery
+        .accounts
+        .iter()
+        .filter(|a| a.unique_asset_id.is_none() && !a.balance.is_zero())
+        .map(|a| a.account_id.to_string())
+        .collect::<Vec<_>>();
+    if !unknown.is_empty() {
+        println!(
+            "{} account(s) hold tokens of an asset no transaction names: {}",
+            unknown.len(),
+            unknown.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+async fn token_supply(
+    service: &Service,
+    registry: &AssetRegistry,
+    args: &TokenSupplyArgs,
+) -> Result<bool, Box<dyn Error>> {
+    use prettytable::{Cell, Row, Table};
+
+    let assets = args
+        .assets
+        .iter()
+        .map(|a| registry.resolve(a))
+        .collect::<Result<Vec<_>, _>>()?;
+    let supplies = token_supply::fetch(service, &assets, args.max_in_flight).await?;
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Asset"),
+        Cell::new("Issued"),
+        Cell::new("Destroyed"),
+        Cell::new("Outstanding"),
+        Cell::new("Events"),
+        Cell::new("Status"),
+    ]));
+    for supply in &supplies {
+        let format = |amount: &U256| registry.format_amount(&supply.unique_asset_id, amount);
+        let status = if supply.problems.is_empty() {
+            "OK"
+        } else {
+            "INCONSISTENT"
+        };
+        table.add_row(Row::new(vec![
+            Cell::new(&registry.label(&supply.unique_asset_id)),
+            Cell::new(&format(&supply.issued)),
+            Cell::new(&format(&supply.destroyed)),
+            Cell::new(&format(&supply.outstanding)),
+            Cell::new(&supply.timeline.len().to_string()),
+            Cell::new(status),
+        ]));
+    }
+    table.printstd();
+
+    for supply in &supplies {
+        let label = registry.label(&supply.unique_asset_id);
+        let format = |amount: &U256| registry.format_amount(&supply.unique_asset_id, amount);
+        if args.timeline {
+            println!("\n{} timeline:", label);
+            let mut timeline = Table::new();
+            timeline.add_row(Row::new(vec![
+                Cell::new("Event"),
+                Cell::new("Change"),
+                Cell::new("Amount"),
+                Cell::new("Supply before"),
+                Cell::new("Supply after"),
+                Cell::new(""),
+            ]));
+            for event in &supply.timeline {
+                let amount = event.amount().map(|a| format(&a)).unwrap_or_default();
+                timeline.add_row(Row::new(vec![
+                    Cell::new(&event.event_ix.to_string()),
+                    Cell::new(event.change.as_str()),
+                    Cell::new(&amount),
+                    Cell::new(&format(&event.previous_amount)),
+                    Cell::new(&format(&event.current_amount)),
+                    Cell::new(if event.gap { "GAP" } else { "" }),
+                ]));
+            }
+            timeline.printstd();
+        }
+        for problem in &supply.problems {
+            println!("[FAIL] {}: {}", label, problem);
+        }
+    }
+
+    Ok(supplies.iter().all(|s| s.problems.is_empty()))
+}
+
+async fn issue_tokens(
+    service: &Service,
+    registry: &AssetRegistry,
+    args: &IssueTokensArgs,
+) -> Result<(), Box<dyn Error>> {
+    let unique_asset_id = registry.resolve(&args.asset)?;
+    let (asset_id, ledger_id) = unique_asset_id.decompose();
+    let contract_id = ledger_contract_id(service, ledger_id).await?;
+    let issuer = User::resolve_alias(&args.issuer)?;
+    let amount = registry.parse_amount(&unique_asset_id, &args.amount)?;
+
+    service.set_identity(issuer.identity);
+    let response = if registry.is_bitwise(&unique_asset_id) {
+        service
+            .issue_positions(&contract_id, &asset_id, &amount)
+            .await?
+    } else {
+        service
+            .led_base_issue_tokens(&contract_id, &asset_id, &amount)
+            .await?
+    };
+
+    println!(
+        "Issued {} {} to {} (tx id {})",
+        registry.format_amount(&unique_asset_id, &amount),
+        registry.label(&unique_asset_id),
+        args.issuer,
+        response.tx_id
+    );
+    Ok(())
+}
+
+async fn transfer_tokens(
+    service: &Service,
+    registry: &AssetRegistry,
+    args: &TransferTokensArgs,
+) -> Result<(), Box<dyn Error>> {
+    let unique_asset_id = registry.resolve(&args.asset)?;
+    let sender = User::resolve_alias(&args.from)?;
+    let receiver = parse_holder(&args.to)?;
+    let amount = registry.parse_amount(&unique_asset_id, &args.amount)?;
+
+    // the preview is informational, only a dry run fails without it
+    match FeeSchedule::load(service, DEFAULT_MAX_IN_FLIGHT).await {
+        Ok(schedule) => println!(
+            "Transfer from {} to {}:\n{}",
+            args.from,
+            receiver.label,
+            schedule
+                .preview(&unique_asset_id, &amount)
+                .render(registry)?
+        ),
+        Err(e) if args.dry_run => return Err(e),
+        Err(e) => warn!(error = %e, "could not read the fees of the transfer"),
+    }
+    if args.dry_run {
+        return Ok(());
+    }
+
+    service.set_identity(sender.identity);
+    let response = if registry.is_bitwise(&unique_asset_id) {
+        service
+            .transfer_positions(
+                &unique_asset_id,
+                &sender.principal,
+                &receiver.principal,
+                &amount,
+            )
+            .await?
+    } else {
+        service
+            .int_transfer_tokens(&unique_asset_id, &receiver.principal, &amount)
+            .await?
+    };
+
+    println!(
+        "Transferred {} {} from {} to {} (tx id {})",
+        registry.format_amount(&unique_asset_id, &amount),
+        registry.label(&unique_asset_id),
+        args.from,
+        receiver.label,
+        response.tx_id
+    );
+    Ok(())
+}
+
+pub async fn bootstrap(
+    service: &Service,
+    environment_path: &Path,
+    args: &BootstrapArgs,
+) -> Result<bool, Box<dyn Error>> {
+    let identity = User::resolve_alias(&args.identity)?;
+    let read_code =
+        |file_path: &Option<PathBuf>| file_path.as_ref().map(std::fs::read_to_string).transpose();
+    let plan = BootstrapPlan {
+        ledger_id: args.ledger_id,
+        decimal_pointer: U256::new(args.decimal_pointer.parse()?)?,
+        integration_code: read_code(&args.integration_code)?,
+        clmp_code: read_code(&args.clmp_code)?,
+        controller_code: read_code(&args.controller_code)?,
+        controllers: args.controllers,
+        controller_owner: identity.principal,
+    };
+
+    let mut environment = Environment::load(environment_path)?.unwrap_or_default();
+    if environment.ledger_id != args.ledger_id {
+        // contracts of a manifest for another ledger are not reused
+        environment = Environment::default();
+    }
+    let mut steps = Vec::new();
+    service.set_identity(identity.identity);
+    let result = environment::bootstrap(
+        service,
+        &plan,
+        &mut environment,
+        environment_path,
+        &mut steps,
+    )
+    .await;
+
+    print_steps(
+        steps
+            .iter()
+            .map(|step| (step.action.as_str(), step.tx_id.as_ref())),
+    );
+    println!(
+        "Environment manifest written to {}",
+        environment_path.display()
+    );
+
+    if let Err(e) = result {
+        println!("[FAIL] bootstrap stopped: {}", e);
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+pub async fn controllers(
+    service: &Service,
+    registry: &AssetRegistry,
+    command: &ControllersCommand,
+) -> Result<bool, Box<dyn Error>> {
+    let ControllersCommand::Deploy(args) = command;
+    let owner = User::resolve_alias(&args.owner)?;
+    let issuer = User::resolve_alias(args.issuer.as_deref().unwrap_or(&args.owner))?;
+
+    let deployment_code = match (&args.deployment_code, &args.copy_from) {
+        (Some(file_path), _) => std::fs::read_to_string(file_path)?,
+        (None, Some(contract_id)) => {
+            service
+                .mng_contract_deployment_code(&contract_id.parse()?)
+                .await?
+        }
+        (None, None) => return Err("either --deployment-code or --copy-from is required".into()),
+    };
+    let unique_asset_id = args
+        .asset
+        .as_deref()
+        .map(|a| registry.resolve(a))
+        .transpose()?;
+    let asset = match &unique_asset_id {
+        Some(unique_asset_id) => {
+            let (asset_id, ledger_id) = unique_asset_id.decompose();
+            Some((ledger_contract_id(service, ledger_id).await?, asset_id))
+        }
+        None => None,
+    };
+    let supplies = args
+        .supplies
+        .iter()
+        .map(|s| s.parse::<Nat>())
+        .collect::<Result<Vec<_>, _>>()?;
+    let blacklist = args
+        .blacklist
+        .iter()
+        .map(|entry| {
+            let (holder, code) = match entry.split_once('=') {
+                Some((holder, code)) => (holder, code.parse::<u8>()?),
+                None => (entry.as_str(), controller::DEFAULT_BLACKLIST_CODE),
+            };
+            Ok((parse_holder(holder)?.principal, code))
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+    let limits = args
+        .limits
+        .iter()
+        .map(|entry| {
+            let (holder, amount) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("expected HOLDER=AMOUNT: {}", entry))?;
+            let unique_asset_id = unique_asset_id.as_ref().ok_or("--limit requires --asset")?;
+            let limit = registry.parse_amount(unique_asset_id, amount)?;
+            Ok((parse_holder(holder)?.principal, limit))
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+    let plan = ControllerPlan {
+        deployment_code,
+        asset,
+        supplies,
+        blacklist,
+        limits,
+    };
+    let report = controller::wire_controller(service, &plan, &owner, &issuer).await;
+
+    print!("{}", report.render());
+
+    Ok(report.succeeded())
+}
+
+pub async fn fees(
+    service: &Service,
+    registry: &AssetRegistry,
+    command: &FeesCommand,
+) -> Result<(), Box<dyn Error>> {
+    match command {
+        FeesCommand::Set(args) => set_fee(service, registry, args).await,
+        FeesCommand::List(args) => list_fees(service, registry, args, false).await,
+        FeesCommand::History(args) => list_fees(service, registry, args, true).await,
+    }
+}
+
+async fn set_fee(
+    service: &Service,
+    registry: &AssetRegistry,
+    args: &SetFeeArgs,
+) -> Result<(), Box<dyn Error>> {
+    let unique_asset_id = registry.resolve(&args.asset)?;
+    let identity = User::resolve_alias(&args.identity)?;
+    let wallet = parse_holder(&args.wallet)?;
+    let fee_amount = registry.parse_amount(&unique_asset_id, &args.amount)?;
+
+    service.set_identity(identity.identity);
+    let response = service
+        .set_price(
+            args.fee_type,
+            &unique_asset_id,
+            &fee_amount,
+            &wallet.principal,
+        )
+        .await?;
+
+    println!(
+        "Set {} fee of {} to {} (wallet {}, tx id {})",
+        args.fee_type,
+        registry.label(&unique_asset_id),
+        registry.format_amount(&unique_asset_id, &fee_amount),
+        wallet.label,
+        response.tx_id
+    );
+    Ok(())
+}
+
+async fn list_fees(
+    service: &Service,
+    registry: &AssetRegistry,
+    args: &ListFeesArgs,
+    full_history: bool,
+) -> Result<(), Box<dyn Error>> {
+    use prettytable::{Cell, Row, Table};
+
+    let unique_asset_id = args
+        .asset
+        .as_deref()
+        .map(|a| registry.resolve(a))
+        .transpose()?;
+    let mut changes =
+        pricing::history(service, unique_asset_id.as_ref(), args.max_in_flight).await?;
+    if !full_history {
+        changes = pricing::current_fees(changes);
+    }
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Event"),
+        Cell::new("Asset"),
+        Cell::new("Fee type"),
+        Cell::new("Fee"),
+    ]));
+    for change in &changes {
+        table.add_row(Row::new(vec![
+            Cell::new(&change.event_ix.to_string()),
+            Cell::new(&registry.label(&change.unique_asset_id)),
+            Cell::new(&change.fee_type.to_string()),
+            Cell::new(&registry.format_amount(&change.unique_asset_id, &change.fee_amount)),
+        ]));
+    }
+    table.printstd();
+    if full_history {
+        println!("{} price change(s)", changes.len());
+    } else {
+        println!("{} fee(s) set", changes.len());
+    }
+
+    Ok(())
+}
+
+async fn held_tokens(
+    service: &Service,
+    registry: &AssetRegistry,
+    args: &HeldTokensArgs,
+) -> Result<(), Box<dyn Error>> {
+    let unique_asset_id = registry.resolve(&args.asset)?;
+    let holder = parse_holder(&args.holder)?;
+
+    if !registry.is_bitwise(&unique_asset_id) {
+        let balance = service
+            .int_get_balance(&unique_asset_id, &holder.principal)
+            .await?;
+        println!(
+            "{} holds {} {}",
+            holder.label,
+            registry.format_amount(&unique_asset_id, &balance),
+            registry.label(&unique_asset_id)
+        );
+        return Ok(());
+    }
+
+    let positions = service
+        .bitwise_positions(&unique_asset_id, &holder.principal)
+        .await?;
+    println!(
+        "{} holds {} token(s) of {}",
+        holder.label,
+        positions.len(),
+        registry.label(&unique_asset_id)
+    );
+    for position in positions {
+        println!("#{}", position);
+    }
+    Ok(())
+}
+
+async fn ledger_contract_id(
+    service: &Service,
+    ledger_id: LedgerId,
+) -> Result<ContractId, Box<dyn Error>> {
+    Ok(service
+        .int_get_ledger_contract_id(&ledger_id)
+        .await?
+        .ok_or("ledger contract not found")?)
+}
+
+fn list_assets(registry: &AssetRegistry) -> Result<(), Box<dyn Error>> {
+    use prettytable::{Cell, Row, Table};
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Symbol"),
+        Cell::new("Decimals"),
+        Cell::new("Ledger"),
+        Cell::new("Unique asset id"),
+        Cell::new("Checksummed"),
+    ]));
+    for asset in registry.assets() {
+        table.add_row(Row::new(vec![
+            Cell::new(&asset.symbol),
+            Cell::new(&asset.decimals.to_string()),
+            Cell::new(&asset.unique_asset_id.ledger_id().to_string()),
+            Cell::new(&asset.unique_asset_id.to_hex()),
+            Cell::new(&asset.unique_asset_id.to_text()),
+        ]));
+    }
+    table.printstd();
+
+    Ok(())
+}
+
+fn register_asset(
+    registry: &AssetRegistry,
+    args: &RegisterAssetArgs,
+) -> Result<(), Box<dyn Error>> {
+    let asset_id = match args.asset_id.strip_prefix("0x") {
+        Some(hex_digits) => Nat(BigUint::parse_bytes(hex_digits.as_bytes(), 16)
+            .ok_or_else(|| format!("invalid hex asset id: {}", args.asset_id))?),
+        None => args.asset_id.parse::<Nat>()?,
+    };
+
+    let unique_asset_id =
+        registry.register(&args.symbol, args.decimals, &asset_id, args.ledger_id)?;
+    registry.save()?;
+
+    println!("{}: {}", args.symbol, unique_asset_id);
+    Ok(())
+}
+
+/// Prints the actions of a multi-step setup with the tx id of each.
+fn print_steps<'s>(steps: impl Iterator<Item = (&'s str, Option<&'s Nat>)>) {
+    print!("{}", report::render_steps(steps));
+}
+
+fn parse_holder(text: &str) -> Result<ReportHolder, Box<dyn Error>> {
+    if let Some(user) = User::from_alias(text) {
+        return Ok(ReportHolder {
+            label: text.to_string(),
+            principal: user.principal,
+        });
+    }
+
+    Ok(ReportHolder {
+        label: text.to_string(),
+        principal: Principal::from_text(text)?,
+    })
+}
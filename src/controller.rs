@@ -0,0 +1,224 @@
+use crate::{report, AssetId, ContractId, Service, SupplyId, TxId, User, U256};
+use candid::Principal;
+use std::{error::Error, fmt::Write};
+
+/// Blacklist code set for addresses without an explicit one.
+pub const DEFAULT_BLACKLIST_CODE: u8 = 1;
+
+/// Everything a new controller is deployed with and wired to.
+pub struct ControllerPlan {
+    pub deployment_code: String,
+    /// Ledger contract and asset whose usage the controller governs
+    pub asset: Option<(ContractId, AssetId)>,
+    pub supplies: Vec<SupplyId>,
+    pub blacklist: Vec<(Principal, u8)>,
+    pub limits: Vec<(Principal, U256)>,
+}
+
+pub struct WiringStep {
+    pub action: String,
+    pub tx_id: Option<TxId>,
+}
+
+/// Outcome of `wire_controller`. Steps completed before a failure are kept, so a partly wired
+/// controller can be finished by hand.
+#[derive(Default)]
+pub struct ControllerReport {
+    pub controller_id: Option<ContractId>,
+    pub steps: Vec<WiringStep>,
+    /// Result of `ctr_validate_usage_controller`, once reached
+    pub valid: Option<bool>,
+    pub error: Option<String>,
+}
+
+impl ControllerReport {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none() && self.valid == Some(true)
+    }
+
+    /// The steps taken, the controller id and why the wiring failed, if it did.
+    pub fn render(&self) -> String {
+        let mut rendered = report::render_steps(
+            self.steps
+                .iter()
+                .map(|step| (step.action.as_str(), step.tx_id.as_ref())),
+        );
+        if let Some(controller_id) = &self.controller_id {
+            let _ = writeln!(rendered, "Controller contract id: {}", controller_id);
+        }
+        if self.valid == Some(false) {
+            let _ = writeln!(
+                rendered,
+                "[FAIL] ctr_validate_usage_controller rejected the controller"
+            );
+        }
+        if let Some(error) = &self.error {
+            let _ = writeln!(rendered, "[FAIL] wiring stopped: {}", error);
+        }
+        rendered
+    }
+
+    fn record(&mut self, action: String, tx_id: Option<TxId>) {
+        self.steps.push(WiringStep { action, tx_id });
+    }
+}
+
+/// Deploys a controller owned by `owner`, assigns it to the usage of an asset and to supplies as
+/// `issuer`, validates it and seeds its blacklist and limits as `owner`.
+pub async fn wire_controller(
+    service: &Service,
+    plan: &ControllerPlan,
+    owner: &User,
+    issuer: &User,
+) -> ControllerReport {
+    let mut report = ControllerReport::default();
+    if let Err(e) = run_steps(service, plan, owner, issuer, &mut report).await {
+        report.error = Some(e.to_string());
+    }
+    report
+}
+
+async fn run_steps(
+    service: &Service,
+    plan: &ControllerPlan,
+    owner: &User,
+    issuer: &User,
+    report: &mut ControllerReport,
+) -> Result<(), Box<dyn Error>> {
+    service.set_identity(owner.identity.clone());
+    let response = service
+        .mng_create_controller(&plan.deployment_code, &owner.principal)
+        .await?;
+    let controller_id = response.data;
+    report.controller_id = Some(controller_id.clone());
+    report.record(
+        format!("create controller {}", controller_id),
+        Some(response.tx_id),
+    );
+
+    service.set_identity(issuer.identity.clone());
+    if let Some((ledger_contract_id, asset_id)) = &plan.asset {
+        let response = service
+            .led_kyc_set_usage_controller(ledger_contract_id, asset_id, &controller_id)
+            .await?;
+        report.record(
+            format!("set usage controller of asset {}", asset_id),
+            Some(response.tx_id),
+        );
+    }
+    for supply_id in &plan.supplies {
+        let response = service
+            .int_set_supply_controller(supply_id, &Some(controller_id.clone()))
+            .await?;
+        report.record(
+            format!("set controller of supply {}", supply_id),
+            Some(response.tx_id),
+        );
+    }
+
+    let valid = service
+        .ctr_validate_usage_controller(&Some(controller_id.clone()))
+        .await?;
+    report.valid = Some(valid);
+    report.record(
+        format!("validate: {}", if valid { "valid" } else { "invalid" }),
+        None,
+    );
+
+    service.set_identity(owner.identity.clone());
+    if !plan.blacklist.is_empty() {
+        let (addresses, codes): (Vec<_>, Vec<_>) = plan.blacklist.iter().cloned().unzip();
+        let response = service
+            .ctr_set_blacklist_array(&controller_id, &addresses, &codes)
+            .await?;
+        report.record(
+            format!("blacklist {} address(es)", addresses.len()),
+            Some(response.tx_id),
+        );
+    }
+    if !plan.limits.is_empty() {
+        let (addresses, limits): (Vec<_>, Vec<_>) = plan.limits.iter().cloned().unzip();
+        let response = service
+            .ctr_set_limit_array(&controller_id, &addresses, &limits)
+            .await?;
+        report.record(
+            format!("set limits of {} address(es)", addresses.len()),
+            Some(response.tx_id),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wired(valid: bool) -> ControllerReport {
+        let mut report = ControllerReport {
+            controller_id: Some(TxId::from(7u8)),
+            ..Default::default()
+        };
+        report.record("create controller 7".to_string(), Some(TxId::from(10u8)));
+        report.record(
+            "set controller of supply 3".to_string(),
+            Some(TxId::from(11u8)),
+        );
+        report.valid = Some(valid);
+        report.record(format!("validate: {}", valid), None);
+        report
+    }
+
+    #[test]
+    fn steps_are_listed_in_order_with_their_tx_ids() {
+        let report = wired(true);
+        assert!(report.succeeded());
+
+        let rendered = report.render();
+        let lines = rendered.lines().collect::<Vec<_>>();
+        let row = |text: &str| lines.iter().position(|l| l.contains(text)).unwrap();
+        let tx_id = |text: &str| lines[row(text)].split('|').nth(2).unwrap().trim();
+        assert!(row("Step") < row("create controller 7"));
+        assert!(row("create controller 7") < row("set controller of supply 3"));
+        assert!(row("set controller of supply 3") < row("validate: true"));
+        assert_eq!(tx_id("create controller 7"), "10");
+        assert_eq!(tx_id("set controller of supply 3"), "11");
+        // validation is a query and has no tx id
+        assert_eq!(tx_id("validate: true"), "");
+        assert!(rendered.contains("Controller contract id: 7"));
+        assert!(!rendered.contains("[FAIL]"));
+    }
+
+    #[test]
+    fn invalid_controllers_fail() {
+        let report = wired(false);
+        assert!(!report.succeeded());
+        assert!(report
+            .render()
+            .contains("[FAIL] ctr_validate_usage_controller rejected the controller"));
+    }
+
+    #[test]
+    fn steps_before_a_failure_are_kept() {
+        let mut report = wired(true);
+        report.error = Some("blacklist rejected".to_string());
+        assert!(!report.succeeded());
+
+        let rendered = report.render();
+        assert!(rendered.contains("set controller of supply 3"));
+        assert!(rendered.contains("[FAIL] wiring stopped: blacklist rejected"));
+    }
+
+    #[test]
+    fn controllers_that_were_never_created_are_not_shown() {
+        let report = ControllerReport {
+            error: Some("deployment code rejected".to_string()),
+            ..Default::default()
+        };
+        assert!(!report.succeeded());
+
+        let rendered = report.render();
+        assert!(!rendered.contains("Controller contract id"));
+        assert!(rendered.contains("[FAIL] wiring stopped: deployment code rejected"));
+    }
+}
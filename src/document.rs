@@ -0,0 +1,165 @@
+use crate::Hash;
+use candid::Nat;
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// SHA-256 of `data` as the `Hash` number the ledger expects.
+pub fn sha_256_nat(data: &[u8]) -> Hash {
+    Nat(BigUint::from_bytes_be(&Sha256::digest(data)))
+}
+
+/// A hash read back from the ledger as a number. The ledger stores hashes big-endian and may drop
+/// leading zero bytes, so hashes are compared as numbers rather than as byte strings.
+pub fn hash_from_bytes(hash_bytes: &[u8]) -> Hash {
+    Nat(BigUint::from_bytes_be(hash_bytes))
+}
+
+/// Anchored hash of a document: the SHA-256 of a file, or of the manifest of a directory.
+pub struct DocumentHash {
+    pub hash: Hash,
+    /// Manifest the hash was computed from, for directories
+    pub manifest: Option<String>,
+}
+
+pub fn hash_document<P: AsRef<Path>>(path: P) -> Result<DocumentHash, Box<dyn Error>> {
+    let path = path.as_ref();
+    if !path.is_dir() {
+        return Ok(DocumentHash {
+            hash: sha_256_nat(&fs::read(path)?),
+            manifest: None,
+        });
+    }
+
+    let manifest = manifest(path)?;
+    Ok(DocumentHash {
+        hash: sha_256_nat(manifest.as_bytes()),
+        manifest: Some(manifest),
+    })
+}
+
+/// Manifest of all files below `dir` in `sha256sum` format: one `<hex digest>  <path>` line per
+/// file, with `/`-separated paths relative to `dir`, sorted by path.
+pub fn manifest(dir: &Path) -> Result<String, Box<dyn Error>> {
+    let mut files = Vec::new();
+    collect_files(dir, &mut files)?;
+
+    let mut lines = files
+        .iter()
+        .map(|file_path| {
+            let relative = file_path
+                .strip_prefix(dir)?
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let digest = hex::encode(Sha256::digest(fs::read(file_path)?));
+            Ok((relative, digest))
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+    lines.sort();
+
+    Ok(lines
+        .into_iter()
+        .map(|(relative, digest)| format!("{}  {}\n", digest, relative))
+        .collect())
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory with `files` written in the given order.
+    fn directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("document-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (relative, content) in files {
+            let file_path = dir.join(relative);
+            fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+            fs::write(file_path, content).unwrap();
+        }
+        dir
+    }
+
+    fn digest(content: &str) -> String {
+        hex::encode(Sha256::digest(content.as_bytes()))
+    }
+
+    #[test]
+    fn manifest_lists_nested_files_by_relative_path() {
+        let dir = directory(
+            "nested",
+            &[("deed.pdf", "deed"), ("annex/plans/floor.png", "floor")],
+        );
+        let manifest = manifest(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            manifest,
+            format!(
+                "{}  annex/plans/floor.png\n{}  deed.pdf\n",
+                digest("floor"),
+                digest("deed")
+            )
+        );
+    }
+
+    #[test]
+    fn manifest_does_not_depend_on_creation_order_or_location() {
+        let files = [("b.txt", "b"), ("a/c.txt", "c"), ("a.txt", "a")];
+        let mut reversed = files;
+        reversed.reverse();
+        let first = directory("order-1", &files);
+        let second = directory("order-2", &reversed);
+
+        let first_hash = hash_document(&first).unwrap();
+        let second_hash = hash_document(&second).unwrap();
+        fs::remove_dir_all(&first).unwrap();
+        fs::remove_dir_all(&second).unwrap();
+
+        assert_eq!(first_hash.hash, second_hash.hash);
+        let manifest = first_hash.manifest.unwrap();
+        let paths = manifest
+            .lines()
+            .map(|line| line.split_once("  ").unwrap().1)
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["a.txt", "a/c.txt", "b.txt"]);
+    }
+
+    #[test]
+    fn changing_a_file_changes_the_hash() {
+        let dir = directory("changed", &[("deed.pdf", "deed"), ("annex/a.txt", "a")]);
+        let before = hash_document(&dir).unwrap().hash;
+        fs::write(dir.join("annex/a.txt"), "A").unwrap();
+        let after = hash_document(&dir).unwrap().hash;
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_ne!(before, after);
+    }
+
+    #[test]
+    fn files_are_hashed_directly() {
+        let dir = directory("file", &[("deed.pdf", "deed")]);
+        let hash = hash_document(dir.join("deed.pdf")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(hash.hash, sha_256_nat(b"deed"));
+        assert!(hash.manifest.is_none());
+    }
+}
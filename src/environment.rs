@@ -0,0 +1,413 @@
+use crate::{ContractId, LedgerId, Service, U256};
+use candid::{Nat, Principal};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{error::Error, fs, path::Path};
+
+/// Contracts of a CoreLedger environment, persisted as a JSON manifest by `bootstrap`.
+///
+/// Contract ids are kept as soon as they are created, so a bootstrap interrupted between
+/// creating the CLMP and linking it resumes with the same contract instead of creating another.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Environment {
+    pub ledger_id: LedgerId,
+    #[serde(default, with = "opt_contract_id")]
+    pub integration_contract_id: Option<ContractId>,
+    #[serde(default, with = "opt_contract_id")]
+    pub ledger_contract_id: Option<ContractId>,
+    /// Whether `ledger_contract_id` is registered under `ledger_id` with `int_set_contract`
+    #[serde(default)]
+    pub ledger_linked: bool,
+    #[serde(default, with = "contract_ids")]
+    pub controllers: Vec<ContractId>,
+}
+
+impl Environment {
+    pub fn load<P: AsRef<Path>>(file_path: P) -> Result<Option<Self>, Box<dyn Error>> {
+        match fs::read_to_string(file_path) {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, file_path: P) -> Result<(), Box<dyn Error>> {
+        fs::write(file_path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Environment of the manifest at `file_path`, or detected on the canister for `ledger_id`
+    /// if there is no manifest or no `file_path`.
+    pub async fn resolve(
+        service: &Service,
+        file_path: Option<&Path>,
+        ledger_id: LedgerId,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::resolve_with(
+            file_path,
+            ledger_id,
+            async || service.mng_get_integration().await,
+            async |ledger_id| service.int_get_ledger_contract_id(ledger_id).await,
+        )
+        .await
+    }
+
+    /// `resolve` with the integration contract and the ledger contract registered for a ledger
+    /// looked up by `integration` and `ledger_contract_of`.
+    async fn resolve_with(
+        file_path: Option<&Path>,
+        ledger_id: LedgerId,
+        mut integration: impl AsyncFnMut() -> Result<Option<ContractId>, Box<dyn Error>>,
+        mut ledger_contract_of: impl AsyncFnMut(&LedgerId) -> Result<Option<ContractId>, Box<dyn Error>>,
+    ) -> Result<Self, Box<dyn Error>> {
+        if let Some(file_path) = file_path {
+            if let Some(environment) = Self::load(file_path)? {
+                if environment.ledger_linked {
+                    let registered = ledger_contract_of(&environment.ledger_id).await?;
+                    environment.check(&registered, file_path)?;
+                }
+                return Ok(environment);
+            }
+        }
+        Ok(Self {
+            ledger_id,
+            integration_contract_id: integration().await?,
+            ledger_contract_id: ledger_contract_of(&ledger_id).await?,
+            ledger_linked: true,
+            controllers: Vec::new(),
+        })
+    }
+
+    /// Checks that the linked ledger contract of the manifest is the `registered` one of the
+    /// canister, so that a manifest of another canister or an outdated one is not used.
+    fn check(
+        &self,
+        registered: &Option<ContractId>,
+        file_path: &Path,
+    ) -> Result<(), Box<dyn Error>> {
+        if *registered == self.ledger_contract_id {
+            return Ok(());
+        }
+        let describe = |contract_id: &Option<ContractId>| {
+            contract_id
+                .as_ref()
+                .map_or_else(|| "none".to_string(), |c| c.to_string())
+        };
+        Err(format!(
+            "manifest {} links ledger {} to contract {} but the canister has {}; run `bootstrap` \
+             again or remove the manifest",
+            file_path.display(),
+            self.ledger_id,
+            describe(&self.ledger_contract_id),
+            describe(registered)
+        )
+        .into())
+    }
+
+    /// Contract id of the linked ledger, or an error pointing to `bootstrap`.
+    pub fn ledger_contract_id(&self) -> Result<&ContractId, Box<dyn Error>> {
+        match &self.ledger_contract_id {
+            Some(contract_id) if self.ledger_linked => Ok(contract_id),
+            _ => Err(format!(
+                "ledger {} is not set up on this canister, run `bootstrap` first",
+                self.ledger_id
+            )
+            .into()),
+        }
+    }
+}
+
+/// Deployment codes and settings of `bootstrap`. Codes are only needed for contracts that do not
+/// exist yet.
+pub struct BootstrapPlan {
+    pub ledger_id: LedgerId,
+    pub decimal_pointer: U256,
+    pub integration_code: Option<String>,
+    pub clmp_code: Option<String>,
+    pub controller_code: Option<String>,
+    /// Number of controllers the environment should have
+    pub controllers: usize,
+    pub controller_owner: Principal,
+}
+
+pub struct BootstrapStep {
+    pub action: String,
+    pub tx_id: Option<Nat>,
+}
+
+/// Brings `environment` up to `plan`: detects the integration and ledger contracts, creates and
+/// links the missing ones and creates controllers until there are `plan.controllers`. The
+/// environment is saved to `manifest_path` after every step, so contracts created before a
+/// failure or an interruption are reused by the next run.
+pub async fn bootstrap(
+    service: &Service,
+    plan: &BootstrapPlan,
+    environment: &mut Environment,
+    manifest_path: &Path,
+    steps: &mut Vec<BootstrapStep>,
+) -> Result<(), Box<dyn Error>> {
+    environment.ledger_id = plan.ledger_id;
+    environment.save(manifest_path)?;
+    let mut step = |action: String, tx_id: Option<Nat>| steps.push(BootstrapStep { action, tx_id });
+    let missing_code = |contract: &str, flag: &str| -> Box<dyn Error> {
+        format!(
+            "no {} contract exists, pass its deployment code with {}",
+            contract, flag
+        )
+        .into()
+    };
+
+    match service.mng_get_integration().await? {
+        Some(contract_id) => {
+            step(format!("integration {} exists", contract_id), None);
+            environment.integration_contract_id = Some(contract_id);
+        }
+        None => {
+            let code = plan
+                .integration_code
+                .as_ref()
+                .ok_or_else(|| missing_code("integration", "--integration-code"))?;
+            let response = service
+                .mng_create_integration(code, &plan.decimal_pointer)
+                .await?;
+            step(
+                format!("create integration {}", response.data),
+                Some(response.tx_id),
+            );
+            environment.integration_contract_id = Some(response.data);
+        }
+    }
+    environment.save(manifest_path)?;
+
+    match service.int_get_ledger_contract_id(&plan.ledger_id).await? {
+        Some(contract_id) => {
+            step(
+                format!("ledger {} is CLMP {}", plan.ledger_id, contract_id),
+                None,
+            );
+            environment.ledger_contract_id = Some(contract_id);
+            environment.ledger_linked = true;
+        }
+        None => {
+            environment.ledger_linked = false;
+            // a CLMP created by an interrupted bootstrap is linked instead of creating another
+            let clmp_contract_id = match environment.ledger_contract_id.clone() {
+                Some(contract_id) => {
+                    service.mng_contract_name(&contract_id).await?;
+                    step(format!("CLMP {} exists", contract_id), None);
+                    contract_id
+                }
+                None => {
+                    let code = plan
+                        .clmp_code
+                        .as_ref()
+                        .ok_or_else(|| missing_code("CLMP", "--clmp-code"))?;
+                    let response = service.mng_create_clmp(code).await?;
+                    step(
+                        format!("create CLMP {}", response.data),
+                        Some(response.tx_id),
+                    );
+                    environment.ledger_contract_id = Some(response.data.clone());
+                    environment.save(manifest_path)?;
+                    response.data
+                }
+            };
+            let response = service
+                .int_set_contract(&clmp_contract_id, &plan.ledger_id)
+                .await?;
+            step(
+                format!(
+                    "link CLMP {} as ledger {}",
+                    clmp_contract_id, plan.ledger_id
+                ),
+                Some(response.tx_id),
+            );
+            environment.ledger_linked = true;
+        }
+    }
+    environment.save(manifest_path)?;
+
+    for contract_id in &environment.controllers {
+        service.mng_contract_name(contract_id).await?;
+        step(format!("controller {} exists", contract_id), None);
+    }
+    while environment.controllers.len() < plan.controllers {
+        let code = plan
+            .controller_code
+            .as_ref()
+            .ok_or_else(|| missing_code("controller", "--controller-code"))?;
+        let response = service
+            .mng_create_controller(code, &plan.controller_owner)
+            .await?;
+        step(
+            format!("create controller {}", response.data),
+            Some(response.tx_id),
+        );
+        environment.controllers.push(response.data);
+        environment.save(manifest_path)?;
+    }
+
+    Ok(())
+}
+
+mod opt_contract_id {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        contract_id: &Option<ContractId>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        contract_id
+            .as_ref()
+            .map(|c| c.0.to_string())
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<ContractId>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|text| text.parse().map_err(de::Error::custom))
+            .transpose()
+    }
+}
+
+mod contract_ids {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        contract_ids: &[ContractId],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        contract_ids
+            .iter()
+            .map(|c| c.0.to_string())
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<ContractId>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|text| text.parse().map_err(de::Error::custom))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::Cell, path::PathBuf};
+
+    fn manifest_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("environment-{}-{}.json", name, std::process::id()))
+    }
+
+    fn linked_environment() -> Environment {
+        Environment {
+            ledger_id: 7,
+            integration_contract_id: Some(Nat::from(11u8)),
+            ledger_contract_id: Some(Nat::from(12u8)),
+            ledger_linked: true,
+            controllers: vec![Nat::from(13u8), Nat::from(14u8)],
+        }
+    }
+
+    fn assert_same(left: &Environment, right: &Environment) {
+        assert_eq!(left.ledger_id, right.ledger_id);
+        assert_eq!(left.integration_contract_id, right.integration_contract_id);
+        assert_eq!(left.ledger_contract_id, right.ledger_contract_id);
+        assert_eq!(left.ledger_linked, right.ledger_linked);
+        assert_eq!(left.controllers, right.controllers);
+    }
+
+    #[test]
+    fn manifest_round_trips() {
+        let path = manifest_path("round-trip");
+        let environment = linked_environment();
+
+        environment.save(&path).unwrap();
+        let loaded = Environment::load(&path).unwrap().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_same(&loaded, &environment);
+    }
+
+    #[test]
+    fn missing_manifest_loads_as_none() {
+        let path = manifest_path("missing");
+        assert!(Environment::load(&path).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn resolve_uses_an_existing_manifest_matching_the_canister() {
+        let path = manifest_path("resolve-existing");
+        let environment = linked_environment();
+        environment.save(&path).unwrap();
+        let looked_up = Cell::new(Vec::new());
+
+        let resolved = Environment::resolve_with(
+            Some(&path),
+            1,
+            async || panic!("the integration is taken from the manifest"),
+            async |ledger_id| {
+                let mut ledger_ids = looked_up.take();
+                ledger_ids.push(*ledger_id);
+                looked_up.set(ledger_ids);
+                Ok(Some(Nat::from(12u8)))
+            },
+        )
+        .await;
+        fs::remove_file(&path).unwrap();
+
+        assert_same(&resolved.unwrap(), &environment);
+        assert_eq!(looked_up.take(), vec![7]);
+    }
+
+    #[tokio::test]
+    async fn resolve_rejects_a_manifest_of_another_canister() {
+        let path = manifest_path("resolve-outdated");
+        linked_environment().save(&path).unwrap();
+
+        let error = Environment::resolve_with(
+            Some(&path),
+            7,
+            async || Ok(None),
+            async |_| Ok(Some(Nat::from(99u8))),
+        )
+        .await
+        .err()
+        .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "manifest {} links ledger 7 to contract 12 but the canister has 99; run \
+                 `bootstrap` again or remove the manifest",
+                path.display()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn resolve_without_a_manifest_detects_the_canister() {
+        let path = manifest_path("resolve-detect");
+
+        let resolved = Environment::resolve_with(
+            Some(&path),
+            3,
+            async || Ok(Some(Nat::from(21u8))),
+            async |ledger_id| Ok(Some(Nat::from(*ledger_id))),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(resolved.ledger_id, 3);
+        assert_eq!(resolved.integration_contract_id, Some(Nat::from(21u8)));
+        assert_eq!(resolved.ledger_contract_id, Some(Nat::from(3u8)));
+        assert!(resolved.ledger_linked);
+        assert!(resolved.controllers.is_empty());
+    }
+}
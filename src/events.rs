@@ -0,0 +1,229 @@
+#![allow(dead_code)]
+
+use crate::batch::{QueryRequest, QueryResponse};
+use crate::metrics::metrics;
+use crate::{
+    AccountUpdate, AdministratorChanged, AmendmentUpdate, AssetUpdate, BlacklistChanged,
+    ContractId, ControllerCreated, LedgerAdded, LedgerId, LimitChanged, LimitConsumed,
+    OwnershipTransferred, PauseChanged, PricingChanged, Service, SupplyUpdate, TokensCreated,
+    TokensDestroyed, Transaction, TransactionEvent,
+};
+use candid::Nat;
+use std::{error::Error, fmt};
+
+/// Event streams exposed by the CoreLedger canister through the `event_*_count` methods.
+#[derive(Clone)]
+pub enum EventStream {
+    AccountUpdate,
+    AdministratorChanged(ContractId),
+    AmendmentUpdate(LedgerId),
+    AssetUpdate(LedgerId),
+    BlacklistChanged(ContractId),
+    ControllerCreated,
+    LedgerAdded,
+    LimitChanged(ContractId),
+    LimitConsumed(ContractId),
+    OwnershipTransferred(ContractId),
+    PauseChanged(ContractId),
+    PricingChanged,
+    SupplyUpdate,
+    TokensCreated,
+    TokensDestroyed,
+}
+
+/// An event of any stream, as returned by `Service::event_get`.
+pub enum Event {
+    AccountUpdate(AccountUpdate),
+    AdministratorChanged(AdministratorChanged),
+    AmendmentUpdate(AmendmentUpdate),
+    AssetUpdate(AssetUpdate),
+    BlacklistChanged(BlacklistChanged),
+    ControllerCreated(ControllerCreated),
+    LedgerAdded(LedgerAdded),
+    LimitChanged(LimitChanged),
+    LimitConsumed(LimitConsumed),
+    OwnershipTransferred(OwnershipTransferred),
+    PauseChanged(PauseChanged),
+    PricingChanged(PricingChanged),
+    SupplyUpdate(SupplyUpdate),
+    TokensCreated(TokensCreated),
+    TokensDestroyed(TokensDestroyed),
+}
+
+impl fmt::Display for EventStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventStream::AccountUpdate => write!(f, "AccountUpdate"),
+            EventStream::AdministratorChanged(c) => write!(f, "AdministratorChanged({c})"),
+            EventStream::AmendmentUpdate(l) => write!(f, "AmendmentUpdate({l})"),
+            EventStream::AssetUpdate(l) => write!(f, "AssetUpdate({l})"),
+            EventStream::BlacklistChanged(c) => write!(f, "BlacklistChanged({c})"),
+            EventStream::ControllerCreated => write!(f, "ControllerCreated"),
+            EventStream::LedgerAdded => write!(f, "LedgerAdded"),
+            EventStream::LimitChanged(c) => write!(f, "LimitChanged({c})"),
+            EventStream::LimitConsumed(c) => write!(f, "LimitConsumed({c})"),
+            EventStream::OwnershipTransferred(c) => write!(f, "OwnershipTransferred({c})"),
+            EventStream::PauseChanged(c) => write!(f, "PauseChanged({c})"),
+            EventStream::PricingChanged => write!(f, "PricingChanged"),
+            EventStream::SupplyUpdate => write!(f, "SupplyUpdate"),
+            EventStream::TokensCreated => write!(f, "TokensCreated"),
+            EventStream::TokensDestroyed => write!(f, "TokensDestroyed"),
+        }
+    }
+}
+
+impl Service {
+    pub async fn event_count(&self, stream: &EventStream) -> Result<u64, Box<dyn Error>> {
+        match stream {
+            EventStream::AccountUpdate => self.event_account_update_count().await,
+            EventStream::AdministratorChanged(c) => self.event_administrator_changed_count(c).await,
+            EventStream::AmendmentUpdate(l) => self.event_amendment_update_count(l).await,
+            EventStream::AssetUpdate(l) => self.event_asset_update_count(l).await,
+            EventStream::BlacklistChanged(c) => self.event_blacklist_changed_count(c).await,
+            EventStream::ControllerCreated => self.event_controller_created_count().await,
+            EventStream::LedgerAdded => self.event_ledger_added_count().await,
+            EventStream::LimitChanged(c) => self.event_limit_changed_count(c).await,
+            EventStream::LimitConsumed(c) => self.event_limit_consumed_count(c).await,
+            EventStream::OwnershipTransferred(c) => self.event_ownership_transferred_count(c).await,
+            EventStream::PauseChanged(c) => self.event_pause_changed_count(c).await,
+            EventStream::PricingChanged => self.event_pricing_changed_count().await,
+            EventStream::SupplyUpdate => self.event_supply_update_count().await,
+            EventStream::TokensCreated => self.event_tokens_created_count().await,
+            EventStream::TokensDestroyed => self.event_tokens_destroyed_count().await,
+        }
+    }
+
+    pub async fn event_get(
+        &self,
+        stream: &EventStream,
+        event_ix: u64,
+    ) -> Result<Option<Event>, Box<dyn Error>> {
+        let ix = &event_ix;
+        let event = match stream {
+            EventStream::AccountUpdate => self
+                .event_account_update_get(ix)
+                .await?
+                .map(Event::AccountUpdate),
+            EventStream::AdministratorChanged(c) => self
+                .event_administrator_changed_get(c, ix)
+                .await?
+                .map(Event::AdministratorChanged),
+            EventStream::AmendmentUpdate(l) => self
+                .event_amendment_update_get(l, ix)
+                .await?
+                .map(Event::AmendmentUpdate),
+            EventStream::AssetUpdate(l) => self
+                .event_asset_update_get(l, ix)
+                .await?
+                .map(Event::AssetUpdate),
+            EventStream::BlacklistChanged(c) => self
+                .event_blacklist_changed_get(c, ix)
+                .await?
+                .map(Event::BlacklistChanged),
+            EventStream::ControllerCreated => self
+                .event_controller_created_get(ix)
+                .await?
+                .map(Event::ControllerCreated),
+            EventStream::LedgerAdded => self
+                .event_ledger_added_get(ix)
+                .await?
+                .map(Event::LedgerAdded),
+            EventStream::LimitChanged(c) => self
+                .event_limit_changed_get(c, ix)
+                .await?
+                .map(Event::LimitChanged),
+            EventStream::LimitConsumed(c) => self
+                .event_limit_consumed_get(c, ix)
+                .await?
+                .map(Event::LimitConsumed),
+            EventStream::OwnershipTransferred(c) => self
+                .event_ownership_transferred_get(c, ix)
+                .await?
+                .map(Event::OwnershipTransferred),
+            EventStream::PauseChanged(c) => self
+                .event_pause_changed_get(c, ix)
+                .await?
+                .map(Event::PauseChanged),
+            EventStream::PricingChanged => self
+                .event_pricing_changed_get(ix)
+                .await?
+                .map(Event::PricingChanged),
+            EventStream::SupplyUpdate => self
+                .event_supply_update_get(ix)
+                .await?
+                .map(Event::SupplyUpdate),
+            EventStream::TokensCreated => self
+                .event_tokens_created_get(ix)
+                .await?
+                .map(Event::TokensCreated),
+            EventStream::TokensDestroyed => self
+                .event_tokens_destroyed_get(ix)
+                .await?
+                .map(Event::TokensDestroyed),
+        };
+        Ok(event)
+    }
+}
+
+/// All events of `stream` with their positions, read with batched queries. The follower lag of
+/// the stream counts down as the events are processed.
+pub async fn fetch_events(
+    service: &Service,
+    stream: EventStream,
+    max_in_flight: usize,
+) -> Result<Vec<(u64, Event)>, Box<dyn Error>> {
+    let count = service.event_count(&stream).await?;
+    let requests = (0..count)
+        .map(|event_ix| QueryRequest::Event {
+            stream: stream.clone(),
+            event_ix,
+        })
+        .collect::<Vec<_>>();
+
+    let stream_label = stream.to_string();
+    metrics().set_event_follower_lag(&stream_label, count);
+
+    let mut events = Vec::new();
+    for (event_ix, result) in (0..count).zip(service.batch_query(&requests, max_in_flight).await) {
+        if let QueryResponse::Event(Some(event)) = result? {
+            events.push((event_ix, event));
+        }
+        metrics().set_event_follower_lag(&stream_label, count - event_ix - 1);
+    }
+    Ok(events)
+}
+
+/// Transactions by id from 0 upwards, until `count` of their events satisfy `wanted` or a whole
+/// batch of ids is unknown. The canister has no query for the transactions of an event, so this
+/// is how events are tied to the other events of the same call.
+pub async fn fetch_transactions(
+    service: &Service,
+    wanted: impl Fn(&TransactionEvent) -> bool,
+    count: usize,
+    max_in_flight: usize,
+) -> Result<Vec<Transaction>, Box<dyn Error>> {
+    let window = 4 * max_in_flight.max(1) as u64;
+    let mut found_events = 0;
+    let mut transactions = Vec::new();
+    let mut next = 0;
+
+    while found_events < count {
+        let requests = (next..next + window)
+            .map(|tx_id| QueryRequest::Transaction(Nat::from(tx_id)))
+            .collect::<Vec<_>>();
+        let mut found = false;
+        for result in service.batch_query(&requests, max_in_flight).await {
+            if let QueryResponse::Transaction(Some(tx)) = result? {
+                found_events += tx.events.iter().filter(|e| wanted(e)).count();
+                transactions.push(tx);
+                found = true;
+            }
+        }
+        if !found {
+            break;
+        }
+        next += window;
+    }
+
+    Ok(transactions)
+}
@@ -0,0 +1,241 @@
+use crate::U256;
+use num_bigint::BigUint;
+use num_traits::Zero;
+use std::{error::Error, fmt};
+
+/// Number of fractional digits shown for human-readable prices.
+const PRICE_PRECISION: u8 = 12;
+
+/// Exchange rate of a supply in the canister's fixed-point representation.
+///
+/// The canister stores how many base units of the offered asset are given for one base unit of
+/// the desired asset, multiplied by the decimal pointer returned by `int_get_decimal_ptr`.
+/// With a decimal pointer of 10^9, "1 BTC = 60000 USD" on a supply offering USD for BTC is
+/// `60000 * 10^9`, and "1 RE = 200 USD" on a supply offering RE for USD is `10^9 / 200`.
+#[derive(Clone)]
+pub struct ExchangeRate {
+    raw: U256,
+    decimal_ptr: U256,
+}
+
+impl ExchangeRate {
+    pub fn from_raw(raw: U256, decimal_ptr: U256) -> Self {
+        Self { raw, decimal_ptr }
+    }
+
+    /// Rate from a price quoted as offered asset per one desired asset, e.g. "60000" for a supply
+    /// offering USD for BTC at 60000 USD per BTC.
+    pub fn from_offered_per_desired(
+        price: &str,
+        decimal_ptr: &U256,
+        offered_decimals: u8,
+        desired_decimals: u8,
+    ) -> Result<Self, Box<dyn Error>> {
+        let (numerator, denominator) = parse_price(price)?;
+        Self::from_ratio(
+            numerator * decimal_ptr.as_biguint() * pow10(offered_decimals),
+            denominator * pow10(desired_decimals),
+            decimal_ptr,
+        )
+    }
+
+    /// Rate from a price quoted as desired asset per one offered asset, e.g. "200" for a supply
+    /// offering RE for USD at 200 USD per RE.
+    pub fn from_desired_per_offered(
+        price: &str,
+        decimal_ptr: &U256,
+        offered_decimals: u8,
+        desired_decimals: u8,
+    ) -> Result<Self, Box<dyn Error>> {
+        let (numerator, denominator) = parse_price(price)?;
+        Self::from_ratio(
+            denominator * decimal_ptr.as_biguint() * pow10(offered_decimals),
+            numerator * pow10(desired_decimals),
+            decimal_ptr,
+        )
+    }
+
+    /// Rate of a supply trading the same pair in the opposite direction at the same price.
+    pub fn inverse(&self) -> Result<Self, Box<dyn Error>> {
+        let decimal_ptr = self.decimal_ptr.as_biguint();
+        Self::from_ratio(
+            decimal_ptr * decimal_ptr,
+            self.raw.as_biguint().clone(),
+            &self.decimal_ptr,
+        )
+    }
+
+    pub fn raw(&self) -> &U256 {
+        &self.raw
+    }
+
+    pub fn offered_per_desired(&self, offered_decimals: u8, desired_decimals: u8) -> String {
+        format_ratio(
+            &(self.raw.as_biguint() * pow10(desired_decimals)),
+            &(self.decimal_ptr.as_biguint() * pow10(offered_decimals)),
+        )
+    }
+
+    pub fn desired_per_offered(&self, offered_decimals: u8, desired_decimals: u8) -> String {
+        format_ratio(
+            &(self.decimal_ptr.as_biguint() * pow10(offered_decimals)),
+            &(self.raw.as_biguint() * pow10(desired_decimals)),
+        )
+    }
+
+    /// Both directions of the price, e.g. `0.005 RE per USD (200 USD per RE)`.
+    pub fn describe(&self, offered: (&str, u8), desired: (&str, u8)) -> String {
+        let (offered_symbol, offered_decimals) = offered;
+        let (desired_symbol, desired_decimals) = desired;
+        format!(
+            "{} {} per {} ({} {} per {})",
+            self.offered_per_desired(offered_decimals, desired_decimals),
+            offered_symbol,
+            desired_symbol,
+            self.desired_per_offered(offered_decimals, desired_decimals),
+            desired_symbol,
+            offered_symbol
+        )
+    }
+
+    /// Rounds `numerator / denominator` to the nearest representable rate.
+    fn from_ratio(
+        numerator: BigUint,
+        denominator: BigUint,
+        decimal_ptr: &U256,
+    ) -> Result<Self, Box<dyn Error>> {
+        if denominator.is_zero() {
+            return Err("exchange rate price must not be zero".into());
+        }
+
+        let quotient = &numerator / &denominator;
+        let remainder = &numerator % &denominator;
+        let rounded = if remainder * 2u8 >= denominator {
+            quotient + 1u8
+        } else {
+            quotient
+        };
+        if rounded.is_zero() {
+            return Err("exchange rate is too small for the decimal pointer".into());
+        }
+
+        Ok(Self {
+            raw: U256::new(rounded)?,
+            decimal_ptr: decimal_ptr.clone(),
+        })
+    }
+}
+
+impl fmt::Display for ExchangeRate {
+    /// Base units of the offered asset per base unit of the desired asset.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_ratio(
+            self.raw.as_biguint(),
+            self.decimal_ptr.as_biguint(),
+        ))
+    }
+}
+
+fn pow10(exponent: u8) -> BigUint {
+    BigUint::from(10u8).pow(exponent as u32)
+}
+
+/// Splits a decimal price such as `0.005` into `5 / 1000`.
+fn parse_price(price: &str) -> Result<(BigUint, BigUint), Box<dyn Error>> {
+    let fraction_digits = price
+        .split_once('.')
+        .map(|(_, fraction)| fraction.len())
+        .unwrap_or(0);
+    let fraction_digits = u8::try_from(fraction_digits).map_err(|_| "price is too precise")?;
+
+    let numerator = U256::parse_units(price, fraction_digits)?;
+    Ok((numerator.as_biguint().clone(), pow10(fraction_digits)))
+}
+
+fn format_ratio(numerator: &BigUint, denominator: &BigUint) -> String {
+    if denominator.is_zero() {
+        return "n/a".to_string();
+    }
+
+    let scaled = (numerator * pow10(PRICE_PRECISION)) / denominator;
+    let formatted = U256::new(scaled)
+        .map(|value| value.format_units(PRICE_PRECISION))
+        .unwrap_or_else(|_| "overflow".to_string());
+
+    match formatted.split_once('.') {
+        Some((whole, fraction)) if fraction.trim_end_matches('0').is_empty() => whole.to_string(),
+        Some((whole, fraction)) => format!("{}.{}", whole, fraction.trim_end_matches('0')),
+        None => formatted,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal_ptr() -> U256 {
+        U256::from(1_000_000_000u64)
+    }
+
+    #[test]
+    fn quotes_in_both_directions_match_the_documented_examples() {
+        let usd_for_btc =
+            ExchangeRate::from_offered_per_desired("60000", &decimal_ptr(), 0, 0).unwrap();
+        assert_eq!(*usd_for_btc.raw(), U256::from(60_000_000_000_000u64));
+
+        let re_for_usd =
+            ExchangeRate::from_desired_per_offered("200", &decimal_ptr(), 0, 0).unwrap();
+        assert_eq!(*re_for_usd.raw(), U256::from(5_000_000u64));
+        assert_eq!(
+            re_for_usd.describe(("RE", 0), ("USD", 0)),
+            "0.005 RE per USD (200 USD per RE)"
+        );
+    }
+
+    #[test]
+    fn display_decimals_scale_the_raw_rate() {
+        // USD with 2 decimals offered for BTC with 8 decimals
+        let rate = ExchangeRate::from_offered_per_desired("60000", &decimal_ptr(), 2, 8).unwrap();
+        assert_eq!(*rate.raw(), U256::from(60_000_000u64));
+        assert_eq!(rate.offered_per_desired(2, 8), "60000");
+        assert_eq!(rate.desired_per_offered(2, 8), "0.000016666666");
+        assert_eq!(rate.to_string(), "0.06");
+    }
+
+    #[test]
+    fn inverse_swaps_the_direction() {
+        let rate = ExchangeRate::from_desired_per_offered("200", &decimal_ptr(), 0, 0).unwrap();
+        let inverse = rate.inverse().unwrap();
+        assert_eq!(*inverse.raw(), U256::from(200_000_000_000u64));
+        assert_eq!(inverse.offered_per_desired(0, 0), "200");
+        assert_eq!(*inverse.inverse().unwrap().raw(), *rate.raw());
+    }
+
+    #[test]
+    fn raw_rates_are_rounded_to_nearest() {
+        // 10^9 / 3 = 333333333.33..
+        let third = ExchangeRate::from_desired_per_offered("3", &decimal_ptr(), 0, 0).unwrap();
+        assert_eq!(*third.raw(), U256::from(333_333_333u64));
+        // 10^9 / 1.5 = 666666666.66..
+        let two_thirds =
+            ExchangeRate::from_desired_per_offered("1.5", &decimal_ptr(), 0, 0).unwrap();
+        assert_eq!(*two_thirds.raw(), U256::from(666_666_667u64));
+    }
+
+    #[test]
+    fn unusable_prices_are_rejected() {
+        let ptr = decimal_ptr();
+        assert!(ExchangeRate::from_desired_per_offered("0", &ptr, 0, 0).is_err());
+        assert!(ExchangeRate::from_offered_per_desired("0.0000000001", &ptr, 0, 0).is_err());
+        assert!(ExchangeRate::from_offered_per_desired("abc", &ptr, 0, 0).is_err());
+        assert!(ExchangeRate::from_offered_per_desired("-1", &ptr, 0, 0).is_err());
+    }
+
+    #[test]
+    fn formatting_handles_a_zero_rate() {
+        let rate = ExchangeRate::from_raw(U256::zero(), decimal_ptr());
+        assert_eq!(rate.offered_per_desired(0, 0), "0");
+        assert_eq!(rate.desired_per_offered(0, 0), "n/a");
+        assert!(rate.inverse().is_err());
+    }
+}
@@ -0,0 +1,52 @@
+use clap::ValueEnum;
+use tracing_subscriber::EnvFilter;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum LogFormat {
+    /// One line per event
+    Compact,
+    /// Multi-line, human-friendly events
+    Pretty,
+    /// Newline-delimited JSON objects
+    Json,
+}
+
+/// Installs the global tracing subscriber writing to stderr, so that reports printed to stdout
+/// stay machine-readable. `RUST_LOG` overrides `level` when set; dependencies only log warnings.
+///
+/// Canister calls are logged as spans with the method, call type, caller and an argument
+/// summary; queries at debug level, updates at info level with latency, tx id and outcome.
+pub fn init(level: LogLevel, format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(format!("warn,demo={}", level.as_str())));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+
+    match format {
+        LogFormat::Compact => builder.compact().init(),
+        LogFormat::Pretty => builder.pretty().init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
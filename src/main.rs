@@ -1,35 +1,153 @@
 mod agent;
+mod amendment;
+mod amount;
+mod assertions;
+mod asset_creation;
+mod audit;
+mod batch;
+mod bitwise;
+mod cache;
+mod cap_table;
+mod cassette;
+mod cli;
+mod commands;
+mod controller;
+mod document;
+mod environment;
+mod events;
+mod exchange_rate;
+mod logging;
+mod metrics;
+mod pacing;
+mod pricing;
+mod read_policy;
+mod registry;
+mod report;
 mod scenarios;
+mod session;
+mod token_supply;
+mod unique_asset_id;
 
 use crate::agent::*;
+use audit::AuditLog;
+use cache::ResponseCache;
+use cassette::Cassette;
+use clap::Parser;
+use cli::{Cli, Command};
+use environment::Environment;
 use ic_agent::{export::Principal, identity::Secp256k1Identity, Agent, Identity};
+use pacing::Pacing;
+use registry::AssetRegistry;
 use scenarios::Scenarios;
-use std::{cell::RefCell, error::Error, io::{self, Write}, path::Path, rc::Rc};
+use session::Session;
+use std::{cell::RefCell, error::Error, path::Path, rc::Rc};
 
 #[tokio::main]
 async fn main() {
-    println!("Welcome to the demo!");
-    print!("Press ENTER to start...");
-    let _ = std::io::stdout().flush();
-    io::stdin().read_line(&mut String::new()).unwrap();
+    let cli = Cli::parse();
+    logging::init(cli.log_level, cli.log_format);
+    let pacing = cli.pacing();
+    let read_policies = cli.read_policies();
+    let (session, cassette) = match open_session(&cli) {
+        Err(e) => panic!("Error while opening cassette: {e}"),
+        Ok(s) => s,
+    };
+    let cache = match cli.response_cache() {
+        Err(e) => panic!("Error while opening cache: {e}"),
+        Ok(c) => c,
+    };
+    let command = cli.command.unwrap_or(Command::Demo);
+    let is_demo = matches!(command, Command::Demo);
 
-    let service = match init_service().await {
+    if is_demo {
+        println!("Welcome to the demo!");
+        pacing.prompt("Press ENTER to start...");
+    }
+
+    if let Some(addr) = cli.metrics_addr {
+        if let Err(e) = metrics::serve(addr).await {
+            panic!("Error while starting the metrics endpoint: {e}");
+        }
+    }
+
+    let service = match init_service(&cli.audit_log, cassette, cache).await {
         Err(e) => panic!("Error while init: {e}"),
         Ok(s) => s,
     };
+    for (method_name, policy) in read_policies {
+        service.set_read_policy(&method_name, policy);
+    }
+    // a recorded run must not depend on local state, or its replay would differ
+    let uses_cassette = cli.record.is_some() || cli.replay.is_some();
+    let registry = if uses_cassette {
+        AssetRegistry::ephemeral()
+    } else {
+        match AssetRegistry::load(&cli.registry) {
+            Err(e) => panic!("Error while loading asset registry: {e}"),
+            Ok(r) => r,
+        }
+    };
+    let environment_path = (!uses_cassette).then_some(cli.environment.as_path());
 
-    if let Err(e) = run(&service).await {
-        panic!("Error during run: {e}")
+    let passed = match run(
+        &service,
+        &registry,
+        &session,
+        &cli.audit_log,
+        environment_path,
+        &command,
+        pacing,
+    )
+    .await
+    {
+        Err(e) => panic!("Error during run: {e}"),
+        Ok(passed) => passed,
     };
 
-    print!("Press ENTER to exit...");
-    let _ = std::io::stdout().flush();
-    io::stdin().read_line(&mut String::new()).unwrap();
+    if let Some(cassette) = service.cassette() {
+        if let Err(e) = cassette.finish() {
+            panic!("Error while closing cassette: {e}");
+        }
+    }
+    if let Some(cache) = service.cache() {
+        if let Err(e) = cache.save() {
+            panic!("Error while saving cache: {e}");
+        }
+    }
+
+    if is_demo {
+        pacing.prompt("Press ENTER to exit...");
+        println!("\n\n");
+    }
+
+    if !passed {
+        std::process::exit(1);
+    }
+}
+
+/// Session of this run: taken from the cassette when replaying, otherwise random and recorded
+/// into a new cassette if requested.
+fn open_session(cli: &Cli) -> Result<(Session, Option<Cassette>), Box<dyn Error>> {
+    if let Some(file_path) = &cli.replay {
+        let cassette = Cassette::replay(file_path)?;
+        let session = Session::from_header(&cassette.header())?;
+        return Ok((session, Some(cassette)));
+    }
 
-    println!("\n\n");
+    let session = Session::random();
+    let cassette = cli
+        .record
+        .as_ref()
+        .map(|file_path| Cassette::record(file_path, session.header()))
+        .transpose()?;
+    Ok((session, cassette))
 }
 
-async fn init_service() -> Result<Service, Box<dyn Error>> {
+async fn init_service(
+    audit_log_path: &Path,
+    cassette: Option<Cassette>,
+    cache: Option<ResponseCache>,
+) -> Result<Service, Box<dyn Error>> {
     // localhost replica
     // let url = "http://localhost:4943";
     // let canister_id = "bkyz2-fmaaa-aaaaa-qaaaq-cai";
@@ -39,23 +157,93 @@ async fn init_service() -> Result<Service, Box<dyn Error>> {
     let url = "https://a4gq6-oaaaa-aaaab-qaa4q-cai.ic0.app";
     let canister_id = "vqhr2-kqaaa-aaaag-alfea-cai";
 
-    println!("Replica address: {}", url);
+    let replaying = cassette.as_ref().is_some_and(|c| c.is_replaying());
+    if replaying {
+        println!("Replaying recorded canister calls, no replica is contacted");
+    } else {
+        println!("Replica address: {}", url);
+    }
     println!("CoreLedger canister id: {}", canister_id);
     println!();
 
     let agent = Agent::builder().with_url(url).build()?;
-    agent.fetch_root_key().await?;
+    if !replaying {
+        agent.fetch_root_key().await?;
+    }
 
     let agent = Rc::new(RefCell::new(agent));
     let canister_id = Principal::from_text(canister_id)?;
 
-    let service = Service::new(Rc::clone(&agent), canister_id);
+    let mut service = Service::new(Rc::clone(&agent), canister_id);
+    // replayed updates never reached the ledger, so they are not audited
+    if !replaying {
+        service = service.with_audit_log(AuditLog::open(audit_log_path)?);
+    }
+    if let Some(cassette) = cassette {
+        service = service.with_cassette(cassette);
+    }
+    if let Some(cache) = cache {
+        service = service.with_cache(cache);
+    }
     Ok(service)
 }
 
-async fn run(service: &Service) -> Result<(), Box<dyn Error>> {
-    let scenarios = Scenarios::init(&service).await;
-    scenarios.run().await
+async fn run(
+    service: &Service,
+    registry: &AssetRegistry,
+    session: &Session,
+    audit_log_path: &Path,
+    environment_path: Option<&Path>,
+    command: &Command,
+    pacing: Pacing,
+) -> Result<bool, Box<dyn Error>> {
+    match command {
+        Command::Demo => {
+            let environment =
+                Environment::resolve(service, environment_path, scenarios::CLMP_LEDGER_ID).await?;
+            run_demo(service, registry, session, &environment, pacing).await
+        }
+        Command::Balances(args) => {
+            commands::balances(service, registry, args).await?;
+            Ok(true)
+        }
+        Command::Assets(command) => {
+            commands::assets(service, registry, environment_path, command).await
+        }
+        Command::Supply(args) => {
+            commands::supply(service, registry, args).await?;
+            Ok(true)
+        }
+        Command::Audit(command) => commands::audit(service, audit_log_path, command).await,
+        Command::Amendments(command) => commands::amendments(service, registry, command).await,
+        Command::Tokens(command) => commands::tokens(service, registry, command).await,
+        Command::Bootstrap(args) => {
+            let environment_path = environment_path.ok_or(
+                "bootstrap writes the environment manifest and cannot be recorded or replayed",
+            )?;
+            commands::bootstrap(service, environment_path, args).await
+        }
+        Command::Controllers(command) => commands::controllers(service, registry, command).await,
+        Command::Fees(command) => {
+            commands::fees(service, registry, command).await?;
+            Ok(true)
+        }
+    }
+}
+
+async fn run_demo(
+    service: &Service,
+    registry: &AssetRegistry,
+    session: &Session,
+    environment: &Environment,
+    pacing: Pacing,
+) -> Result<bool, Box<dyn Error>> {
+    let scenarios = Scenarios::init(service, registry, session, environment, pacing)?;
+    scenarios.run().await?;
+
+    let assertions = scenarios.assertions();
+    assertions.print_summary();
+    Ok(assertions.failed() == 0)
 }
 
 struct User {
@@ -64,6 +252,19 @@ struct User {
 }
 
 impl User {
+    /// Aliases of the identities in `./identities`.
+    pub const ALIASES: [&'static str; 4] = ["alice", "bob", "charlie", "exchange"];
+
+    pub fn from_alias(alias: &str) -> Option<Self> {
+        Self::ALIASES
+            .contains(&alias)
+            .then(|| Self::from_pem(format!("./identities/{}.pem", alias)))
+    }
+
+    pub fn resolve_alias(alias: &str) -> Result<Self, Box<dyn Error>> {
+        Self::from_alias(alias).ok_or_else(|| format!("unknown identity alias: {}", alias).into())
+    }
+
     pub fn from_pem<P: AsRef<Path>>(file_path: P) -> Self {
         let identity = Secp256k1Identity::from_pem_file(file_path).unwrap();
         let principal = identity.sender().unwrap();
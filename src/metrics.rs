@@ -0,0 +1,216 @@
+use ic_agent::AgentError;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::{error::Error, net::SocketAddr, sync::OnceLock, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use tracing::{info, warn};
+
+/// Client-side metrics of canister calls, recorded always and exported by `serve` on request.
+pub struct Metrics {
+    registry: Registry,
+    calls: IntCounterVec,
+    errors: IntCounterVec,
+    latency: HistogramVec,
+    event_follower_lag: IntGaugeVec,
+}
+
+/// Error categories of failed calls.
+pub enum ErrorCategory {
+    /// The canister rejected or trapped
+    Reject,
+    Timeout,
+    /// The replica could not be reached or answered with an HTTP error
+    Transport,
+    /// Response signatures or certificates could not be verified
+    Certificate,
+    /// The response could not be decoded into the expected Candid type
+    Decode,
+    /// A verified read differed from its certified update
+    ReadMismatch,
+    Other,
+}
+
+impl ErrorCategory {
+    pub fn of(error: &AgentError) -> Self {
+        match error {
+            AgentError::CertifiedReject(_) | AgentError::UncertifiedReject(_) => {
+                ErrorCategory::Reject
+            }
+            AgentError::TimeoutWaitingForResponse() => ErrorCategory::Timeout,
+            AgentError::HttpError(_)
+            | AgentError::TransportError(_)
+            | AgentError::InvalidReplicaUrl(_)
+            | AgentError::RouteProviderError(_)
+            | AgentError::MissingReplicaTransport()
+            | AgentError::ResponseSizeExceededLimit() => ErrorCategory::Transport,
+            AgentError::CertificateVerificationFailed()
+            | AgentError::QuerySignatureVerificationFailed
+            | AgentError::CertificateNotAuthorized()
+            | AgentError::CertificateOutdated(_)
+            | AgentError::CertificateHasTooManyDelegations
+            | AgentError::MissingSignature
+            | AgentError::MalformedSignature
+            | AgentError::MalformedPublicKey
+            | AgentError::TooManySignatures { .. }
+            | AgentError::DerKeyLengthMismatch { .. }
+            | AgentError::DerPrefixMismatch { .. } => ErrorCategory::Certificate,
+            _ => ErrorCategory::Other,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCategory::Reject => "reject",
+            ErrorCategory::Timeout => "timeout",
+            ErrorCategory::Transport => "transport",
+            ErrorCategory::Certificate => "certificate",
+            ErrorCategory::Decode => "decode",
+            ErrorCategory::ReadMismatch => "read_mismatch",
+            ErrorCategory::Other => "other",
+        }
+    }
+}
+
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+
+        let calls = IntCounterVec::new(
+            Opts::new("coreledger_calls_total", "Canister calls by method"),
+            &["method", "call_type"],
+        )
+        .unwrap();
+        let errors = IntCounterVec::new(
+            Opts::new(
+                "coreledger_call_errors_total",
+                "Failed canister calls by method and error category",
+            ),
+            &["method", "call_type", "category"],
+        )
+        .unwrap();
+        let latency = HistogramVec::new(
+            HistogramOpts::new(
+                "coreledger_call_duration_seconds",
+                "Latency of canister calls",
+            )
+            .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0]),
+            &["call_type"],
+        )
+        .unwrap();
+        let event_follower_lag = IntGaugeVec::new(
+            Opts::new(
+                "coreledger_event_follower_lag",
+                "Events of a stream not yet processed by the local follower",
+            ),
+            &["stream"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(calls.clone())).unwrap();
+        registry.register(Box::new(errors.clone())).unwrap();
+        registry.register(Box::new(latency.clone())).unwrap();
+        registry
+            .register(Box::new(event_follower_lag.clone()))
+            .unwrap();
+
+        Self {
+            registry,
+            calls,
+            errors,
+            latency,
+            event_follower_lag,
+        }
+    }
+
+    pub fn observe_call(&self, method_name: &str, call_type: &str, latency: Duration) {
+        self.calls
+            .with_label_values(&[method_name, call_type])
+            .inc();
+        self.latency
+            .with_label_values(&[call_type])
+            .observe(latency.as_secs_f64());
+    }
+
+    pub fn observe_error(&self, method_name: &str, call_type: &str, category: ErrorCategory) {
+        self.errors
+            .with_label_values(&[method_name, call_type, category.as_str()])
+            .inc();
+    }
+
+    pub fn set_event_follower_lag(&self, stream: &str, lag: u64) {
+        self.event_follower_lag
+            .with_label_values(&[stream])
+            .set(lag as i64);
+    }
+
+    /// All metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> Result<String, Box<dyn Error>> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+/// Serves the metrics on every HTTP request to `addr` until the process exits.
+pub async fn serve(addr: SocketAddr) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(addr).await?;
+    info!(%addr, "serving metrics");
+
+    tokio::spawn(async move {
+        loop {
+            let mut stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!(error = %e, "metrics connection failed");
+                    continue;
+                }
+            };
+
+            tokio::spawn(async move {
+                // the request itself is irrelevant, every path answers with the metrics
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request).await;
+
+                let response = match metrics().render() {
+                    Ok(body) => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    ),
+                    Err(e) => format!(
+                        "HTTP/1.1 500 Internal Server Error\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        e.to_string().len(),
+                        e
+                    ),
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follower_lag_is_exported_per_stream() {
+        metrics().set_event_follower_lag("AssetUpdate(7)", 3);
+        metrics().set_event_follower_lag("PricingChanged", 0);
+
+        let rendered = metrics().render().unwrap();
+        assert!(rendered.contains(r#"coreledger_event_follower_lag{stream="AssetUpdate(7)"} 3"#));
+        assert!(rendered.contains(r#"coreledger_event_follower_lag{stream="PricingChanged"} 0"#));
+    }
+}
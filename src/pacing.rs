@@ -0,0 +1,48 @@
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+/// Controls how the demo advances between steps: either waiting for the operator to press ENTER
+/// (live presentations) or running straight through with an optional delay (CI, screencasts).
+#[derive(Clone, Copy)]
+pub struct Pacing {
+    interactive: bool,
+    step_delay: Duration,
+}
+
+impl Pacing {
+    pub fn new(interactive: bool, step_delay: Duration) -> Self {
+        Self {
+            interactive,
+            step_delay,
+        }
+    }
+
+    /// Announces the next scenario step and waits until it may start.
+    pub async fn step(&self, title: &str) {
+        if self.interactive {
+            print!("{} (press ENTER...)", title);
+            Self::wait_for_enter();
+        } else {
+            println!("{}...", title);
+        }
+
+        if !self.step_delay.is_zero() {
+            tokio::time::sleep(self.step_delay).await;
+        }
+    }
+
+    /// Shows a prompt and waits for ENTER; skipped entirely in non-interactive mode.
+    pub fn prompt(&self, text: &str) {
+        if self.interactive {
+            print!("{}", text);
+            Self::wait_for_enter();
+        }
+    }
+
+    fn wait_for_enter() {
+        let _ = io::stdout().flush();
+        io::stdin().read_line(&mut String::new()).unwrap();
+    }
+}
@@ -0,0 +1,289 @@
+use crate::{
+    events::{self, Event, EventStream},
+    registry::AssetRegistry,
+    Response, Service, UniqueAssetId, U256,
+};
+use candid::Principal;
+use std::{error::Error, fmt};
+
+/// Fee types of `int_set_price`, reported as `event_id` by `PricingChanged` events.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeeType {
+    /// Charged on `int_transfer_tokens`
+    Transfer,
+    /// Charged on `int_run_warp`, in the asset put into the warp
+    Warp,
+    /// A code this client has no name for, passed through as is
+    Unknown(u8),
+}
+
+impl FeeType {
+    pub fn code(self) -> u8 {
+        match self {
+            FeeType::Transfer => 0,
+            FeeType::Warp => 1,
+            FeeType::Unknown(code) => code,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            0 => FeeType::Transfer,
+            1 => FeeType::Warp,
+            code => FeeType::Unknown(code),
+        }
+    }
+}
+
+impl fmt::Display for FeeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeeType::Transfer => write!(f, "transfer"),
+            FeeType::Warp => write!(f, "warp"),
+            FeeType::Unknown(code) => write!(f, "type {}", code),
+        }
+    }
+}
+
+/// Parses `transfer`, `warp` or a raw fee type code.
+pub fn parse_fee_type(text: &str) -> Result<FeeType, String> {
+    match text {
+        "transfer" => Ok(FeeType::Transfer),
+        "warp" => Ok(FeeType::Warp),
+        code => code
+            .parse::<u8>()
+            .map(FeeType::from_code)
+            .map_err(|_| format!("expected transfer, warp or a code 0-255: {}", text)),
+    }
+}
+
+/// A `PricingChanged` event.
+pub struct PriceChange {
+    pub event_ix: u64,
+    pub fee_type: FeeType,
+    pub unique_asset_id: UniqueAssetId,
+    pub fee_amount: U256,
+}
+
+/// All price changes, oldest first, optionally of a single asset.
+pub async fn history(
+    service: &Service,
+    unique_asset_id: Option<&UniqueAssetId>,
+    max_in_flight: usize,
+) -> Result<Vec<PriceChange>, Box<dyn Error>> {
+    let mut changes = Vec::new();
+    for (event_ix, event) in
+        events::fetch_events(service, EventStream::PricingChanged, max_in_flight).await?
+    {
+        if let Event::PricingChanged(event) = event {
+            if unique_asset_id.is_none_or(|u| *u == event.unique_asset_id) {
+                changes.push(PriceChange {
+                    event_ix,
+                    fee_type: FeeType::from_code(event.event_id),
+                    unique_asset_id: event.unique_asset_id,
+                    fee_amount: event.new_fee_amount,
+                });
+            }
+        }
+    }
+    Ok(changes)
+}
+
+/// Current fees: the latest change of every asset and fee type, in order of first appearance.
+pub fn current_fees(history: Vec<PriceChange>) -> Vec<PriceChange> {
+    let mut fees: Vec<PriceChange> = Vec::new();
+    for change in history {
+        match fees.iter_mut().find(|fee| {
+            fee.unique_asset_id == change.unique_asset_id && fee.fee_type == change.fee_type
+        }) {
+            Some(fee) => *fee = change,
+            None => fees.push(change),
+        }
+    }
+    fees
+}
+
+/// Current fees of all assets, read once from the `PricingChanged` events and used for every
+/// preview of a run.
+pub struct FeeSchedule {
+    fees: Vec<PriceChange>,
+}
+
+impl FeeSchedule {
+    pub async fn load(service: &Service, max_in_flight: usize) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            fees: current_fees(history(service, None, max_in_flight).await?),
+        })
+    }
+
+    /// Current fees of an asset by fee type, zero fees left out.
+    pub fn fees_of(&self, unique_asset_id: &UniqueAssetId) -> Vec<(FeeType, U256)> {
+        self.fees
+            .iter()
+            .filter(|fee| fee.unique_asset_id == *unique_asset_id && !fee.fee_amount.is_zero())
+            .map(|fee| (fee.fee_type, fee.fee_amount.clone()))
+            .collect()
+    }
+
+    pub fn preview(&self, unique_asset_id: &UniqueAssetId, amount: &U256) -> FeePreview {
+        FeePreview {
+            unique_asset_id: *unique_asset_id,
+            amount: amount.clone(),
+            fees: self.fees_of(unique_asset_id),
+        }
+    }
+}
+
+/// Amount of a transfer or warp with the fees currently set on its asset, shown before it is
+/// submitted. Which fee type applies to which call is not documented, so every fee of the asset
+/// is listed and the total is an upper bound.
+pub struct FeePreview {
+    pub unique_asset_id: UniqueAssetId,
+    pub amount: U256,
+    pub fees: Vec<(FeeType, U256)>,
+}
+
+impl FeePreview {
+    pub fn render(&self, registry: &AssetRegistry) -> Result<String, Box<dyn Error>> {
+        let label = registry.label(&self.unique_asset_id);
+        let format = |amount: &U256| registry.format_amount(&self.unique_asset_id, amount);
+        let mut lines = vec![format!(
+            "  {:<13} {} {}",
+            "amount",
+            format(&self.amount),
+            label
+        )];
+        if self.fees.is_empty() {
+            lines.push(format!("  {:<13} none set", "fees"));
+            return Ok(lines.join("\n"));
+        }
+        for (fee_type, fee) in &self.fees {
+            let fee_name = format!("fee {}", fee_type);
+            lines.push(format!("  {:<13} {} {}", fee_name, format(fee), label));
+        }
+        // token positions and a fee in tokens of the same asset do not add up
+        if !registry.is_bitwise(&self.unique_asset_id) {
+            let mut total = self.amount.clone();
+            for (_, fee) in &self.fees {
+                total = total.checked_add(fee)?;
+            }
+            lines.push(format!(
+                "  {:<13} at most {} {}",
+                "total",
+                format(&total),
+                label
+            ));
+        }
+        Ok(lines.join("\n"))
+    }
+}
+
+impl Service {
+    pub async fn set_price(
+        &self,
+        fee_type: FeeType,
+        unique_asset_id: &UniqueAssetId,
+        fee_amount: &U256,
+        wallet: &Principal,
+    ) -> Result<Response, Box<dyn Error>> {
+        self.int_set_price(&fee_type.code(), unique_asset_id, fee_amount, wallet)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Nat;
+
+    fn asset(asset_id: u8) -> UniqueAssetId {
+        UniqueAssetId::compose(&Nat::from(asset_id), 1).unwrap()
+    }
+
+    fn change(event_ix: u64, fee_type: u8, asset_id: u8, fee_amount: u32) -> PriceChange {
+        PriceChange {
+            event_ix,
+            fee_type: FeeType::from_code(fee_type),
+            unique_asset_id: asset(asset_id),
+            fee_amount: U256::from(fee_amount),
+        }
+    }
+
+    fn summary(fees: &[PriceChange]) -> Vec<(u64, u8, U256)> {
+        fees.iter()
+            .map(|fee| (fee.event_ix, fee.fee_type.code(), fee.fee_amount.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn latest_change_of_each_asset_and_fee_type_wins() {
+        let fees = current_fees(vec![
+            change(0, 0, 1, 10),
+            change(1, 1, 1, 20),
+            change(2, 0, 2, 30),
+            change(3, 0, 1, 15),
+            change(4, 1, 1, 0),
+        ]);
+        assert_eq!(
+            summary(&fees),
+            vec![
+                (3, 0, U256::from(15u8)),
+                (4, 1, U256::zero()),
+                (2, 0, U256::from(30u8)),
+            ]
+        );
+        assert!(fees[2].unique_asset_id == asset(2));
+    }
+
+    #[test]
+    fn no_changes_means_no_fees() {
+        assert!(current_fees(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn schedule_lists_nonzero_fees_of_an_asset() {
+        let schedule = FeeSchedule {
+            fees: current_fees(vec![
+                change(0, 0, 1, 10),
+                change(1, 1, 1, 20),
+                change(2, 1, 1, 0),
+                change(3, 7, 2, 5),
+            ]),
+        };
+        assert_eq!(
+            schedule.fees_of(&asset(1)),
+            vec![(FeeType::Transfer, U256::from(10u8))]
+        );
+        assert_eq!(
+            schedule.fees_of(&asset(2)),
+            vec![(FeeType::Unknown(7), U256::from(5u8))]
+        );
+        assert!(schedule.fees_of(&asset(3)).is_empty());
+
+        let preview = schedule.preview(&asset(2), &U256::from(100u8));
+        assert_eq!(preview.amount, U256::from(100u8));
+        assert_eq!(preview.fees, vec![(FeeType::Unknown(7), U256::from(5u8))]);
+    }
+
+    #[test]
+    fn fee_types_round_trip_through_their_codes() {
+        for code in 0..=u8::MAX {
+            assert_eq!(FeeType::from_code(code).code(), code);
+        }
+        assert_eq!(FeeType::from_code(0), FeeType::Transfer);
+        assert_eq!(FeeType::from_code(1), FeeType::Warp);
+        assert_eq!(FeeType::from_code(9), FeeType::Unknown(9));
+    }
+
+    #[test]
+    fn fee_types_parse_from_names_and_codes() {
+        assert_eq!(parse_fee_type("transfer"), Ok(FeeType::Transfer));
+        assert_eq!(parse_fee_type("1"), Ok(FeeType::Warp));
+        assert_eq!(parse_fee_type("42"), Ok(FeeType::Unknown(42)));
+        assert!(parse_fee_type("256").is_err());
+        assert!(parse_fee_type("storage").is_err());
+
+        assert_eq!(FeeType::Warp.to_string(), "warp");
+        assert_eq!(FeeType::Unknown(42).to_string(), "type 42");
+    }
+}
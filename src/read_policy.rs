@@ -0,0 +1,61 @@
+use clap::ValueEnum;
+use std::{collections::HashMap, error::Error, fmt};
+
+/// Query methods whose results feed settlement decisions.
+pub const CRITICAL_READS: &[&str] = &["int_get_balance", "int_get_supply"];
+
+/// How `Service` executes a query method.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum ReadPolicy {
+    /// Query answered by a single replica
+    Fast,
+    /// Query cross-checked against the same call executed as an update, which goes through
+    /// consensus; differing responses fail the read
+    Verified,
+}
+
+/// Per-method read policies; methods without an entry are read with `ReadPolicy::Fast`.
+#[derive(Default)]
+pub struct ReadPolicies {
+    policies: HashMap<String, ReadPolicy>,
+}
+
+impl ReadPolicies {
+    pub fn get(&self, method_name: &str) -> ReadPolicy {
+        self.policies
+            .get(method_name)
+            .copied()
+            .unwrap_or(ReadPolicy::Fast)
+    }
+
+    pub fn set(&mut self, method_name: &str, policy: ReadPolicy) {
+        self.policies.insert(method_name.to_string(), policy);
+    }
+}
+
+/// Parses `METHOD=POLICY`, e.g. `int_get_balance=verified`.
+pub fn parse_read_policy(text: &str) -> Result<(String, ReadPolicy), String> {
+    let (method_name, policy) = text
+        .split_once('=')
+        .ok_or_else(|| format!("expected METHOD=POLICY, got {}", text))?;
+    let policy = ReadPolicy::from_str(policy, true)?;
+    Ok((method_name.to_string(), policy))
+}
+
+/// A verified read whose query response differs from the certified update response.
+#[derive(Debug)]
+pub struct ReadDiscrepancy {
+    pub method_name: String,
+}
+
+impl fmt::Display for ReadDiscrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "query response of {} differs from its certified update response",
+            self.method_name
+        )
+    }
+}
+
+impl Error for ReadDiscrepancy {}
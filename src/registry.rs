@@ -0,0 +1,291 @@
+use crate::{
+    bitwise, events::EventStream, metrics::metrics, AssetId, AssetUpdateCode, ContractId, LedgerId,
+    Service, UniqueAssetId, U256,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Registry entry mapping a human-readable symbol to an asset on the ledger.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AssetInfo {
+    pub symbol: String,
+    pub decimals: u8,
+    pub unique_asset_id: UniqueAssetId,
+    /// Balances are bitmasks of individual tokens rather than amounts
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bitwise: bool,
+}
+
+/// Asset id reserved with `led_base_create_asset` (or about to be) that is not activated yet.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReservedAsset {
+    pub symbol: String,
+    pub decimals: u8,
+    pub unique_asset_id: UniqueAssetId,
+    /// Identity alias that reserved the id and has to activate it
+    pub issuer: String,
+    pub reserved_at: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct RegistryFile {
+    assets: Vec<AssetInfo>,
+    #[serde(default)]
+    synced_asset_events: BTreeMap<LedgerId, u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    reserved: Vec<ReservedAsset>,
+}
+
+/// Local registry of asset symbols and display decimals, persisted as JSON.
+///
+/// Entries are added when the client creates an asset and discovered from `AssetUpdate` events,
+/// so that commands, tables and log lines can use symbols instead of unique asset ids.
+pub struct AssetRegistry {
+    /// Not set for a registry that is only kept for the run
+    file_path: Option<PathBuf>,
+    content: RefCell<RegistryFile>,
+}
+
+impl AssetRegistry {
+    pub fn load<P: AsRef<Path>>(file_path: P) -> Result<Self, Box<dyn Error>> {
+        let file_path = file_path.as_ref().to_path_buf();
+        let content = match fs::read_to_string(&file_path) {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => RegistryFile::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            file_path: Some(file_path),
+            content: RefCell::new(content),
+        })
+    }
+
+    /// An empty registry that is never written, for runs that must not depend on local files.
+    pub fn ephemeral() -> Self {
+        Self {
+            file_path: None,
+            content: RefCell::new(RegistryFile::default()),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let Some(file_path) = &self.file_path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&*self.content.borrow())?;
+        fs::write(file_path, json)?;
+        Ok(())
+    }
+
+    pub fn assets(&self) -> Vec<AssetInfo> {
+        self.content.borrow().assets.clone()
+    }
+
+    /// Registers an asset under `symbol`. If the symbol already names another asset (e.g. from a
+    /// previous demo run), that entry is renamed to `SYMBOL@<unique asset id>` so symbols stay
+    /// unambiguous.
+    pub fn register(
+        &self,
+        symbol: &str,
+        decimals: u8,
+        asset_id: &AssetId,
+        ledger_id: LedgerId,
+    ) -> Result<UniqueAssetId, Box<dyn Error>> {
+        let unique_asset_id = UniqueAssetId::compose(asset_id, ledger_id)?;
+
+        let mut content = self.content.borrow_mut();
+        content
+            .assets
+            .retain(|a| a.unique_asset_id != unique_asset_id);
+        for asset in content.assets.iter_mut().filter(|a| a.symbol == symbol) {
+            asset.symbol = format!("{}@{}", symbol, asset.unique_asset_id);
+        }
+        content.assets.push(AssetInfo {
+            symbol: symbol.to_string(),
+            decimals,
+            unique_asset_id,
+            bitwise: false,
+        });
+
+        Ok(unique_asset_id)
+    }
+
+    /// Remembers a reserved asset id until `remove_reservation` is called on activation.
+    pub fn add_reservation(&self, reservation: ReservedAsset) {
+        let mut content = self.content.borrow_mut();
+        content
+            .reserved
+            .retain(|r| r.unique_asset_id != reservation.unique_asset_id);
+        content.reserved.push(reservation);
+    }
+
+    pub fn reservations(&self) -> Vec<ReservedAsset> {
+        self.content.borrow().reserved.clone()
+    }
+
+    /// Reservation by symbol or any textual form of its unique asset id.
+    pub fn reservation(&self, text: &str) -> Option<ReservedAsset> {
+        let unique_asset_id = text.parse::<UniqueAssetId>().ok();
+        let content = self.content.borrow();
+        content
+            .reserved
+            .iter()
+            .find(|r| r.symbol == text || Some(r.unique_asset_id) == unique_asset_id)
+            .cloned()
+    }
+
+    pub fn remove_reservation(&self, unique_asset_id: &UniqueAssetId) {
+        self.content
+            .borrow_mut()
+            .reserved
+            .retain(|r| r.unique_asset_id != *unique_asset_id);
+    }
+
+    pub fn by_symbol(&self, symbol: &str) -> Option<AssetInfo> {
+        let content = self.content.borrow();
+        content.assets.iter().find(|a| a.symbol == symbol).cloned()
+    }
+
+    pub fn by_unique_asset_id(&self, unique_asset_id: &UniqueAssetId) -> Option<AssetInfo> {
+        let content = self.content.borrow();
+        content
+            .assets
+            .iter()
+            .find(|a| a.unique_asset_id == *unique_asset_id)
+            .cloned()
+    }
+
+    /// Symbol of the asset, or its hex unique asset id if the asset is unknown.
+    pub fn label(&self, unique_asset_id: &UniqueAssetId) -> String {
+        match self.by_unique_asset_id(unique_asset_id) {
+            Some(asset) => asset.symbol,
+            None => unique_asset_id.to_hex(),
+        }
+    }
+
+    /// Display decimals of the asset; unknown assets are shown in base units.
+    pub fn decimals(&self, unique_asset_id: &UniqueAssetId) -> u8 {
+        self.by_unique_asset_id(unique_asset_id)
+            .map(|a| a.decimals)
+            .unwrap_or(0)
+    }
+
+    pub fn set_bitwise(&self, unique_asset_id: &UniqueAssetId, bitwise: bool) {
+        let mut content = self.content.borrow_mut();
+        for asset in content
+            .assets
+            .iter_mut()
+            .filter(|a| a.unique_asset_id == *unique_asset_id)
+        {
+            asset.bitwise = bitwise;
+        }
+    }
+
+    pub fn is_bitwise(&self, unique_asset_id: &UniqueAssetId) -> bool {
+        self.by_unique_asset_id(unique_asset_id)
+            .is_some_and(|a| a.bitwise)
+    }
+
+    /// Parses an amount, or token positions such as `0-3,7` for bitwise assets.
+    pub fn parse_amount(
+        &self,
+        unique_asset_id: &UniqueAssetId,
+        text: &str,
+    ) -> Result<U256, Box<dyn Error>> {
+        if self.is_bitwise(unique_asset_id) {
+            return Ok(bitwise::parse_positions(text)?);
+        }
+        Ok(U256::parse_units(text, self.decimals(unique_asset_id))?)
+    }
+
+    pub fn format_amount(&self, unique_asset_id: &UniqueAssetId, amount: &U256) -> String {
+        if self.is_bitwise(unique_asset_id) {
+            return bitwise::format_positions(amount);
+        }
+        amount.format_units(self.decimals(unique_asset_id))
+    }
+
+    /// Resolves a symbol or any textual form of a unique asset id.
+    pub fn resolve(&self, text: &str) -> Result<UniqueAssetId, Box<dyn Error>> {
+        if let Some(asset) = self.by_symbol(text) {
+            return Ok(asset.unique_asset_id);
+        }
+
+        text.parse::<UniqueAssetId>()
+            .map_err(|_| format!("unknown asset symbol or id: {text}").into())
+    }
+
+    /// Scans `AssetUpdate` events of the ledger that were not seen yet and registers every
+    /// created asset missing from the registry under a placeholder symbol. Returns the number
+    /// of newly registered assets.
+    pub async fn sync(
+        &self,
+        service: &Service,
+        ledger_id: LedgerId,
+        ledger_contract_id: &ContractId,
+    ) -> Result<usize, Box<dyn Error>> {
+        let start = self
+            .content
+            .borrow()
+            .synced_asset_events
+            .get(&ledger_id)
+            .copied()
+            .unwrap_or(0);
+        let count = service.event_asset_update_count(&ledger_id).await?;
+        let stream = EventStream::AssetUpdate(ledger_id).to_string();
+
+        let mut registered = 0;
+        for event_ix in start..count {
+            metrics().set_event_follower_lag(&stream, count - event_ix);
+            let Some(event) = service
+                .event_asset_update_get(&ledger_id, &event_ix)
+                .await?
+            else {
+                continue;
+            };
+            service.invalidate_asset(ledger_contract_id, &event.asset_id)?;
+            if !matches!(
+                event.event_id,
+                AssetUpdateCode::AssetCreationSuccess
+                    | AssetUpdateCode::AssetActivationSuccess
+                    | AssetUpdateCode::AssetCreationActivationSuccess
+            ) {
+                continue;
+            }
+
+            let unique_asset_id = UniqueAssetId::compose(&event.asset_id, ledger_id)?;
+            if self.by_unique_asset_id(&unique_asset_id).is_some() {
+                continue;
+            }
+            let Some(asset) = service
+                .led_base_get_asset(ledger_contract_id, &event.asset_id)
+                .await?
+            else {
+                continue;
+            };
+
+            let symbol = format!(
+                "ASSET-{}",
+                hex::encode_upper(event.asset_id.0.to_bytes_be())
+            );
+            self.register(&symbol, 0, &event.asset_id, ledger_id)?;
+            self.set_bitwise(&unique_asset_id, asset.bitwise);
+            registered += 1;
+        }
+
+        self.content
+            .borrow_mut()
+            .synced_asset_events
+            .insert(ledger_id, count);
+        metrics().set_event_follower_lag(&stream, 0);
+
+        Ok(registered)
+    }
+}
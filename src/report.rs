@@ -0,0 +1,434 @@
+use crate::batch::{QueryRequest, QueryResponse};
+use crate::bitwise;
+use crate::{Service, UniqueAssetId, U256};
+use candid::Nat;
+use candid::Principal;
+use chrono::prelude::*;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::{collections::HashMap, error::Error, fmt::Write, fs, path::Path};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ReportFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+pub struct ReportHolder {
+    pub label: String,
+    pub principal: Principal,
+}
+
+pub struct ReportAsset {
+    pub label: String,
+    pub unique_asset_id: UniqueAssetId,
+    pub decimals: u8,
+    pub bitwise: bool,
+}
+
+impl ReportAsset {
+    /// Display amount, or the held token positions of a bitwise asset.
+    fn format(&self, amount: &U256) -> String {
+        if self.bitwise {
+            return bitwise::format_positions(amount);
+        }
+        amount.format_units(self.decimals)
+    }
+}
+
+/// Single balance of a snapshot; amounts are kept in base units as plain decimal strings so that
+/// snapshots are readable and can be diffed by other tools.
+#[derive(Serialize, Deserialize)]
+pub struct BalanceEntry {
+    pub asset: String,
+    pub unique_asset_id: String,
+    pub holder: String,
+    pub principal: String,
+    pub balance: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BalanceSnapshot {
+    pub taken_at: String,
+    pub entries: Vec<BalanceEntry>,
+}
+
+impl BalanceSnapshot {
+    pub fn load<P: AsRef<Path>>(file_path: P) -> Result<Self, Box<dyn Error>> {
+        let json = fs::read_to_string(file_path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, file_path: P) -> Result<(), Box<dyn Error>> {
+        fs::write(file_path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Balance matrix of arbitrary holders and assets, optionally compared with a previous snapshot.
+pub struct BalanceReport {
+    holders: Vec<ReportHolder>,
+    assets: Vec<ReportAsset>,
+    balances: Vec<Vec<U256>>,
+    taken_at: DateTime<Local>,
+    previous: HashMap<(String, String), U256>,
+}
+
+impl BalanceReport {
+    pub async fn fetch(
+        service: &Service,
+        holders: Vec<ReportHolder>,
+        assets: Vec<ReportAsset>,
+        max_in_flight: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        let requests = assets
+            .iter()
+            .flat_map(|asset| {
+                holders.iter().map(|holder| QueryRequest::Balance {
+                    unique_asset_id: asset.unique_asset_id,
+                    holder: holder.principal,
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut fetched = service
+            .batch_query(&requests, max_in_flight)
+            .await
+            .into_iter()
+            .map(|result| result.and_then(QueryResponse::into_balance))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter();
+
+        let balances = assets
+            .iter()
+            .map(|_| fetched.by_ref().take(holders.len()).collect())
+            .collect();
+
+        Ok(Self {
+            holders,
+            assets,
+            balances,
+            taken_at: Local::now(),
+            previous: HashMap::new(),
+        })
+    }
+
+    pub fn with_previous(mut self, snapshot: &BalanceSnapshot) -> Result<Self, Box<dyn Error>> {
+        for entry in &snapshot.entries {
+            let key = (entry.unique_asset_id.clone(), entry.principal.clone());
+            self.previous.insert(key, entry.balance.parse::<U256>()?);
+        }
+        Ok(self)
+    }
+
+    pub fn snapshot(&self) -> BalanceSnapshot {
+        let mut entries = Vec::new();
+        for (asset, row) in self.assets.iter().zip(&self.balances) {
+            for (holder, balance) in self.holders.iter().zip(row) {
+                entries.push(BalanceEntry {
+                    asset: asset.label.clone(),
+                    unique_asset_id: asset.unique_asset_id.to_string(),
+                    holder: holder.label.clone(),
+                    principal: holder.principal.to_text(),
+                    balance: balance.to_string(),
+                    previous: self.previous_balance(asset, holder).map(|p| p.to_string()),
+                });
+            }
+        }
+
+        BalanceSnapshot {
+            taken_at: self.taken_at.to_rfc3339(),
+            entries,
+        }
+    }
+
+    pub fn render(&self, format: ReportFormat) -> Result<String, Box<dyn Error>> {
+        match format {
+            ReportFormat::Table => Ok(self.render_table()),
+            ReportFormat::Csv => Ok(self.render_csv()),
+            ReportFormat::Json => Ok(serde_json::to_string_pretty(&self.snapshot())?),
+        }
+    }
+
+    fn render_table(&self) -> String {
+        use prettytable::{Cell, Row, Table};
+
+        let mut table = Table::new();
+
+        let mut header = vec![Cell::new(&format!("{:<15}", ""))];
+        for holder in &self.holders {
+            header.push(Cell::new(&format!("{:<15}", holder.label)));
+        }
+        table.add_row(Row::new(header));
+
+        for (asset, row) in self.assets.iter().zip(&self.balances) {
+            let mut cells = vec![Cell::new(&asset.label)];
+            for (holder, balance) in self.holders.iter().zip(row) {
+                let formatted = asset.format(balance);
+                let text = match self.delta(asset, holder, balance) {
+                    Some(delta) => format!("{} ({})", formatted, delta),
+                    None => formatted,
+                };
+                cells.push(Cell::new(&text));
+            }
+            table.add_row(Row::new(cells));
+        }
+
+        table.to_string()
+    }
+
+    fn render_csv(&self) -> String {
+        let with_diff = !self.previous.is_empty();
+
+        let mut csv = String::from("asset,unique_asset_id,holder,principal,balance");
+        if with_diff {
+            csv.push_str(",previous,delta");
+        }
+        csv.push('\n');
+
+        for (asset, row) in self.assets.iter().zip(&self.balances) {
+            for (holder, balance) in self.holders.iter().zip(row) {
+                let _ = write!(
+                    csv,
+                    "{},{},{},{},{}",
+                    csv_field(&asset.label),
+                    asset.unique_asset_id,
+                    csv_field(&holder.label),
+                    holder.principal,
+                    balance
+                );
+                if with_diff {
+                    let previous = self.previous_balance(asset, holder);
+                    let _ = write!(
+                        csv,
+                        ",{},{}",
+                        previous.map(|p| p.to_string()).unwrap_or_default(),
+                        self.delta(asset, holder, balance).unwrap_or_default()
+                    );
+                }
+                csv.push('\n');
+            }
+        }
+
+        csv
+    }
+
+    fn previous_balance(&self, asset: &ReportAsset, holder: &ReportHolder) -> Option<&U256> {
+        let key = (
+            asset.unique_asset_id.to_string(),
+            holder.principal.to_text(),
+        );
+        self.previous.get(&key)
+    }
+
+    fn delta(&self, asset: &ReportAsset, holder: &ReportHolder, balance: &U256) -> Option<String> {
+        if self.previous.is_empty() {
+            return None;
+        }
+
+        let previous = match self.previous_balance(asset, holder) {
+            None => return Some("new".to_string()),
+            Some(previous) => previous,
+        };
+        if asset.bitwise {
+            return Some(bitwise_delta(previous, balance));
+        }
+        let delta = match balance.cmp(previous) {
+            Ordering::Equal => return Some("0".to_string()),
+            Ordering::Greater => format!(
+                "+{}",
+                balance
+                    .checked_sub(previous)
+                    .ok()?
+                    .format_units(asset.decimals)
+            ),
+            Ordering::Less => format!(
+                "-{}",
+                previous
+                    .checked_sub(balance)
+                    .ok()?
+                    .format_units(asset.decimals)
+            ),
+        };
+        Some(delta)
+    }
+}
+
+/// Token positions gained and lost, e.g. `+#4 -#0-1`.
+fn bitwise_delta(previous: &U256, balance: &U256) -> String {
+    let gained = bitwise::difference(balance, previous);
+    let lost = bitwise::difference(previous, balance);
+    match (gained.is_zero(), lost.is_zero()) {
+        (true, true) => "0".to_string(),
+        (false, true) => format!("+{}", bitwise::format_positions(&gained)),
+        (true, false) => format!("-{}", bitwise::format_positions(&lost)),
+        (false, false) => format!(
+            "+{} -{}",
+            bitwise::format_positions(&gained),
+            bitwise::format_positions(&lost)
+        ),
+    }
+}
+
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Table of the actions of a multi-step setup with the tx id of each, if it made one.
+pub fn render_steps<'s>(steps: impl Iterator<Item = (&'s str, Option<&'s Nat>)>) -> String {
+    use prettytable::{Cell, Row, Table};
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![Cell::new("Step"), Cell::new("Tx id")]));
+    for (action, tx_id) in steps {
+        let tx_id = tx_id.map(|t| t.to_string()).unwrap_or_default();
+        table.add_row(Row::new(vec![Cell::new(action), Cell::new(&tx_id)]));
+    }
+    table.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holder(label: &str, id: u8) -> ReportHolder {
+        ReportHolder {
+            label: label.to_string(),
+            principal: Principal::from_slice(&[id]),
+        }
+    }
+
+    fn asset(label: &str, asset_id: u8, decimals: u8, bitwise: bool) -> ReportAsset {
+        ReportAsset {
+            label: label.to_string(),
+            unique_asset_id: UniqueAssetId::compose(&Nat::from(asset_id), 1).unwrap(),
+            decimals,
+            bitwise,
+        }
+    }
+
+    /// Report of `balances[asset][holder]`, compared with `previous` if it has entries.
+    fn report(
+        holders: Vec<ReportHolder>,
+        assets: Vec<ReportAsset>,
+        balances: Vec<Vec<u32>>,
+        previous: &[(usize, usize, u32)],
+    ) -> BalanceReport {
+        BalanceReport {
+            balances: balances
+                .into_iter()
+                .map(|row| row.into_iter().map(U256::from).collect())
+                .collect(),
+            taken_at: Local::now(),
+            previous: previous
+                .iter()
+                .map(|(asset_ix, holder_ix, balance)| {
+                    let key = (
+                        assets[*asset_ix].unique_asset_id.to_string(),
+                        holders[*holder_ix].principal.to_text(),
+                    );
+                    (key, U256::from(*balance))
+                })
+                .collect(),
+            holders,
+            assets,
+        }
+    }
+
+    fn deltas(report: &BalanceReport) -> Vec<Option<String>> {
+        let mut deltas = Vec::new();
+        for (asset, row) in report.assets.iter().zip(&report.balances) {
+            for (holder, balance) in report.holders.iter().zip(row) {
+                deltas.push(report.delta(asset, holder, balance));
+            }
+        }
+        deltas
+    }
+
+    #[test]
+    fn no_snapshot_means_no_deltas() {
+        let report = report(
+            vec![holder("alice", 1)],
+            vec![asset("USD", 1, 2, false)],
+            vec![vec![150]],
+            &[],
+        );
+        assert_eq!(deltas(&report), vec![None]);
+    }
+
+    #[test]
+    fn deltas_are_signed_and_use_the_asset_decimals() {
+        let report = report(
+            vec![holder("alice", 1), holder("bob", 2), holder("charlie", 3)],
+            vec![asset("USD", 1, 2, false), asset("RE", 2, 0, false)],
+            vec![vec![250, 75, 10], vec![5, 5, 5]],
+            &[(0, 0, 100), (0, 1, 100), (0, 2, 10), (1, 0, 7)],
+        );
+        let expected = ["+1.50", "-0.25", "0", "-2", "new", "new"];
+        assert_eq!(
+            deltas(&report),
+            expected.map(|d| Some(d.to_string())).to_vec()
+        );
+    }
+
+    #[test]
+    fn bitwise_deltas_list_gained_and_lost_positions() {
+        assert_eq!(bitwise_delta(&U256::from(0b11u8), &U256::from(0b11u8)), "0");
+        assert_eq!(
+            bitwise_delta(&U256::from(0b11u8), &U256::from(0b111u8)),
+            "+#2"
+        );
+        assert_eq!(
+            bitwise_delta(&U256::from(0b111u8), &U256::from(0b1u8)),
+            "-#1-2"
+        );
+        assert_eq!(
+            bitwise_delta(&U256::from(0b0011u8), &U256::from(0b10000u8)),
+            "+#4 -#0-1"
+        );
+
+        let report = report(
+            vec![holder("alice", 1)],
+            vec![asset("ART", 1, 0, true)],
+            vec![vec![0b10000]],
+            &[(0, 0, 0b0011)],
+        );
+        assert_eq!(deltas(&report), vec![Some("+#4 -#0-1".to_string())]);
+    }
+
+    #[test]
+    fn csv_fields_with_separators_are_quoted() {
+        assert_eq!(csv_field("alice"), "alice");
+        assert_eq!(csv_field("Smith, Jane"), "\"Smith, Jane\"");
+        assert_eq!(csv_field("the \"fund\""), "\"the \"\"fund\"\"\"");
+        assert_eq!(csv_field("line\nbreak"), "\"line\nbreak\"");
+    }
+
+    #[test]
+    fn csv_quotes_labels_and_adds_the_diff_columns() {
+        let report = report(
+            vec![holder("Smith, \"Jr\"", 1)],
+            vec![asset("US\nD", 1, 2, false)],
+            vec![vec![250]],
+            &[(0, 0, 100)],
+        );
+        let unique_asset_id = &report.assets[0].unique_asset_id;
+        let principal = report.holders[0].principal;
+
+        assert_eq!(
+            report.render_csv(),
+            format!(
+                "asset,unique_asset_id,holder,principal,balance,previous,delta\n\
+                 \"US\nD\",{},\"Smith, \"\"Jr\"\"\",{},250,100,+1.50\n",
+                unique_asset_id, principal
+            )
+        );
+    }
+}
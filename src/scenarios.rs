@@ -1,36 +1,57 @@
 use crate::{
-    AssetId, ContractId, CreateSupplyRequest, LedgerId, RunWarpRequest, Service, SupplyId,
-    UniqueAssetId, User, U256,
+    assertions::Assertions,
+    asset_creation::{self, CreateAssetRequest},
+    batch::DEFAULT_MAX_IN_FLIGHT,
+    document,
+    environment::Environment,
+    events::EventStream,
+    exchange_rate::ExchangeRate,
+    pacing::Pacing,
+    pricing::FeeSchedule,
+    registry::AssetRegistry,
+    report::{BalanceReport, ReportAsset, ReportFormat, ReportHolder},
+    session::Session,
+    ContractId, CreateSupplyRequest, LedgerId, RunWarpRequest, Service, SupplyId, UniqueAssetId,
+    User, U256,
 };
 use candid::{Nat, Principal};
-use chrono::prelude::*;
-use num_bigint::BigUint;
-use rand::Rng;
-use sha2::{Digest, Sha256};
-use std::{
-    error::Error,
-    io::{self, Write},
-};
+use ic_agent::{agent::RejectCode, AgentError};
+use std::{error::Error, future::Future};
+use tracing::warn;
 
 pub struct Scenarios<'a> {
     service: &'a Service,
     clmp_contract_id: ContractId,
+    ledger_id: LedgerId,
+    assertions: Assertions,
+    pacing: Pacing,
+    registry: &'a AssetRegistry,
+    session: &'a Session,
 }
 
-const CLMP_LEDGER_ID: LedgerId = 1;
+pub const CLMP_LEDGER_ID: LedgerId = 1;
 
 impl<'a> Scenarios<'a> {
-    pub async fn init(service: &'a Service) -> Self {
-        let clmp_contract_id = service
-            .int_get_ledger_contract_id(&CLMP_LEDGER_ID)
-            .await
-            .unwrap()
-            .unwrap();
-
-        Self {
+    pub fn init(
+        service: &'a Service,
+        registry: &'a AssetRegistry,
+        session: &'a Session,
+        environment: &Environment,
+        pacing: Pacing,
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
             service,
-            clmp_contract_id,
-        }
+            clmp_contract_id: environment.ledger_contract_id()?.clone(),
+            ledger_id: environment.ledger_id,
+            assertions: Assertions::default(),
+            pacing,
+            registry,
+            session,
+        })
+    }
+
+    pub fn assertions(&self) -> &Assertions {
+        &self.assertions
     }
 
     pub async fn run(&self) -> Result<(), Box<dyn Error>> {
@@ -39,7 +60,6 @@ impl<'a> Scenarios<'a> {
 
     pub async fn dfinity_demo(&self) -> Result<(), Box<dyn Error>> {
         println!();
-        let mut stdin_buffer = String::new();
 
         // get users
         let alice = User::alice();
@@ -53,9 +73,9 @@ impl<'a> Scenarios<'a> {
 
         let req_asset_re = CreateAssetRequest {
             asset_hash: Self::calculate_sha_256("Real Estate Token")?,
-            ..CreateAssetRequest::default()
+            ..self.new_asset_request().await?
         };
-        let unique_asset_id_re = self.create_asset(&req_asset_re).await?;
+        let unique_asset_id_re = self.create_asset("RE", &req_asset_re).await?;
 
         // get asset
         let asset_re = self
@@ -63,39 +83,42 @@ impl<'a> Scenarios<'a> {
             .led_base_get_asset(&self.clmp_contract_id, &req_asset_re.asset_id)
             .await?;
         println!(
-            "0x{}: {}",
-            hex::encode_upper(&unique_asset_id_re.0.to_bytes_be()),
+            "RE ({}): {}",
+            unique_asset_id_re,
             serde_json::to_string_pretty(&asset_re).unwrap()
         );
 
+        let tokens_created_before = self
+            .service
+            .event_count(&EventStream::TokensCreated)
+            .await?;
+
         // issue 1000000 tokens
         self.service
             .led_base_issue_tokens(
                 &self.clmp_contract_id,
                 &req_asset_re.asset_id,
-                &Nat::from(1_000_000_u32),
+                &self.amount(&unique_asset_id_re, "1,000,000")?,
             )
             .await?;
 
-        print!("Creating exchange assets (press ENTER...)");
-        let _ = std::io::stdout().flush();
-        io::stdin().read_line(&mut stdin_buffer).unwrap();
+        self.pacing.step("Creating exchange assets").await;
 
         // create USD asset
         self.service.set_identity(exchange.identity.clone());
 
         let req_asset_usd = CreateAssetRequest {
             asset_hash: Self::calculate_sha_256("USD")?,
-            ..CreateAssetRequest::default()
+            ..self.new_asset_request().await?
         };
-        let unique_asset_id_usd = self.create_asset(&req_asset_usd).await?;
+        let unique_asset_id_usd = self.create_asset("USD", &req_asset_usd).await?;
         println!("USD: {}", unique_asset_id_usd);
 
         self.service
             .led_base_issue_tokens(
                 &self.clmp_contract_id,
                 &req_asset_usd.asset_id,
-                &Nat::from(20e9 as u128),
+                &self.amount(&unique_asset_id_usd, "20,000,000,000")?,
             )
             .await?;
 
@@ -104,22 +127,20 @@ impl<'a> Scenarios<'a> {
 
         let req_asset_btc = CreateAssetRequest {
             asset_hash: Self::calculate_sha_256("BTC")?,
-            ..CreateAssetRequest::default()
+            ..self.new_asset_request().await?
         };
-        let unique_asset_id_btc = self.create_asset(&req_asset_btc).await?;
+        let unique_asset_id_btc = self.create_asset("BTC", &req_asset_btc).await?;
         println!("BTC: {}", unique_asset_id_btc);
 
         self.service
             .led_base_issue_tokens(
                 &self.clmp_contract_id,
                 &req_asset_btc.asset_id,
-                &Nat::from(181_000_u32),
+                &self.amount(&unique_asset_id_btc, "181,000")?,
             )
             .await?;
 
-        print!("Show balances (press ENTER...)");
-        let _ = std::io::stdout().flush();
-        io::stdin().read_line(&mut stdin_buffer).unwrap();
+        self.pacing.step("Show balances").await;
 
         println!("\nInitial holdings:");
         let print_balances_request = PrintBalancesRequest {
@@ -134,46 +155,111 @@ impl<'a> Scenarios<'a> {
         self.print_balances(&print_balances_request).await?;
         println!();
 
-        print!("Transfer 100 RE from Alice to Bob (press ENTER...)");
-        let _ = std::io::stdout().flush();
-        io::stdin().read_line(&mut stdin_buffer).unwrap();
+        self.expect_balance("RE", &unique_asset_id_re, "Alice", &alice, 1_000_000_u32)
+            .await;
+        self.expect_balance(
+            "USD",
+            &unique_asset_id_usd,
+            "Exchange",
+            &exchange,
+            20_000_000_000_u64,
+        )
+        .await;
+        self.expect_balance(
+            "BTC",
+            &unique_asset_id_btc,
+            "Exchange",
+            &exchange,
+            181_000_u32,
+        )
+        .await;
+        self.expect_event_count(EventStream::TokensCreated, tokens_created_before, 3)
+            .await;
+
+        self.pacing.step("Transfer 100 RE from Alice to Bob").await;
 
         self.service.set_identity(alice.identity.clone());
         self.service
-            .int_transfer_tokens(&unique_asset_id_re, &bob.principal, &Nat::from(100_u8))
+            .int_transfer_tokens(
+                &unique_asset_id_re,
+                &bob.principal,
+                &self.amount(&unique_asset_id_re, "100")?,
+            )
             .await?;
 
-        print!("Transfer 11 BTC from Exchange to Charlie (press ENTER...)");
-        let _ = std::io::stdout().flush();
-        io::stdin().read_line(&mut stdin_buffer).unwrap();
+        self.pacing
+            .step("Transfer 11 BTC from Exchange to Charlie")
+            .await;
 
         self.service.set_identity(exchange.identity.clone());
         self.service
-            .int_transfer_tokens(&unique_asset_id_btc, &charlie.principal, &Nat::from(11u8))
+            .int_transfer_tokens(
+                &unique_asset_id_btc,
+                &charlie.principal,
+                &self.amount(&unique_asset_id_btc, "11")?,
+            )
             .await?;
 
         println!("\nHoldings after transfer:");
         self.print_balances(&print_balances_request).await?;
         println!();
 
-        print!("Create supplies (press ENTER...)");
-        let _ = std::io::stdout().flush();
-        io::stdin().read_line(&mut stdin_buffer).unwrap();
+        self.expect_balance("RE", &unique_asset_id_re, "Alice", &alice, 999_900_u32)
+            .await;
+        self.expect_balance("RE", &unique_asset_id_re, "Bob", &bob, 100_u32)
+            .await;
+        self.expect_balance(
+            "BTC",
+            &unique_asset_id_btc,
+            "Exchange",
+            &exchange,
+            180_989_u32,
+        )
+        .await;
+        self.expect_balance("BTC", &unique_asset_id_btc, "Charlie", &charlie, 11_u32)
+            .await;
+
+        // Bob only holds 100 RE, so the canister must reject this transfer
+        self.service.set_identity(bob.identity.clone());
+        self.expect_failure(
+            "transfer of 1000 RE from Bob to Alice is rejected",
+            self.service.int_transfer_tokens(
+                &unique_asset_id_re,
+                &alice.principal,
+                &self.amount(&unique_asset_id_re, "1000")?,
+            ),
+        )
+        .await;
+        self.expect_balance("RE", &unique_asset_id_re, "Bob", &bob, 100_u32)
+            .await;
 
-        let valid_until = Local::now()
+        self.pacing.step("Create supplies").await;
+
+        let valid_until = self
+            .session
+            .now()
             .checked_add_days(chrono::Days::new(10))
             .unwrap()
             .timestamp_nanos_opt()
             .unwrap() as u64;
 
+        let supply_updates_before = self.service.event_count(&EventStream::SupplyUpdate).await?;
+        let decimal_ptr = self.service.int_get_decimal_ptr().await?;
+
         // create supply (1 RE = 200 USD)
         println!("RE => USD");
         self.service.set_identity(alice.identity.clone());
+        let rate_re_usd = ExchangeRate::from_desired_per_offered(
+            "200",
+            &decimal_ptr,
+            self.registry.decimals(&unique_asset_id_re),
+            self.registry.decimals(&unique_asset_id_usd),
+        )?;
         let req_supply_re_usd = CreateSupplyRequest {
-            offered: unique_asset_id_re.clone(),
-            max_amount: Nat::from(1e5 as u128),
-            desired: unique_asset_id_usd.clone(),
-            exchange_rate: U256::from(5e6 as u128),
+            offered: unique_asset_id_re,
+            max_amount: self.amount(&unique_asset_id_re, "100,000")?,
+            desired: unique_asset_id_usd,
+            exchange_rate: rate_re_usd.raw().clone(),
             valid_until,
 
             controller: None,
@@ -186,16 +272,26 @@ impl<'a> Scenarios<'a> {
             .int_create_supply(&req_supply_re_usd)
             .await?
             .data;
-        println!("RE => USD supply id: {}", supply_id_re_usd);
+        println!(
+            "RE => USD supply id: {}, rate: {}",
+            supply_id_re_usd,
+            self.describe_rate(&rate_re_usd, &unique_asset_id_re, &unique_asset_id_usd)
+        );
 
         // create supply (1 BTC = 60000 USD)
         println!("USD => BTC");
         self.service.set_identity(exchange.identity.clone());
+        let rate_usd_btc = ExchangeRate::from_offered_per_desired(
+            "60000",
+            &decimal_ptr,
+            self.registry.decimals(&unique_asset_id_usd),
+            self.registry.decimals(&unique_asset_id_btc),
+        )?;
         let req_supply_usd_btc = CreateSupplyRequest {
-            offered: unique_asset_id_usd.clone(),
-            max_amount: Nat::from(10e5 as u128),
-            desired: unique_asset_id_btc.clone(),
-            exchange_rate: U256::from(6e13 as u128),
+            offered: unique_asset_id_usd,
+            max_amount: self.amount(&unique_asset_id_usd, "1,000,000")?,
+            desired: unique_asset_id_btc,
+            exchange_rate: rate_usd_btc.raw().clone(),
             valid_until,
 
             controller: None,
@@ -208,154 +304,190 @@ impl<'a> Scenarios<'a> {
             .int_create_supply(&req_supply_usd_btc)
             .await?
             .data;
-        println!("USD => BTC supply id: {}", supply_id_usd_btc);
+        println!(
+            "USD => BTC supply id: {}, rate: {}",
+            supply_id_usd_btc,
+            self.describe_rate(&rate_usd_btc, &unique_asset_id_usd, &unique_asset_id_btc)
+        );
 
-        print!("\nRun warp (press ENTER...)");
-        let _ = std::io::stdout().flush();
-        io::stdin().read_line(&mut stdin_buffer).unwrap();
+        self.expect_supply_open_amount("RE => USD", &supply_id_re_usd, 100_000_u32)
+            .await;
+        self.expect_supply_open_amount("USD => BTC", &supply_id_usd_btc, 1_000_000_u32)
+            .await;
+        self.expect_event_count(EventStream::SupplyUpdate, supply_updates_before, 2)
+            .await;
+
+        println!();
+        self.pacing.step("Run warp").await;
 
         self.service.set_identity(charlie.identity.clone());
 
-        let warp_amount = U256::from(1 as u128);
-        let warp_supplies = Vec::from([supply_id_usd_btc, supply_id_re_usd]);
-        self.run_warp(warp_supplies, warp_amount).await?;
+        let warp_amount = self.amount(&unique_asset_id_btc, "1")?;
+        let warp_supplies = Vec::from([supply_id_usd_btc.clone(), supply_id_re_usd.clone()]);
+        self.run_warp(&unique_asset_id_btc, warp_supplies, warp_amount)
+            .await?;
 
-        print!("Show balances (press ENTER...)");
-        let _ = std::io::stdout().flush();
-        io::stdin().read_line(&mut stdin_buffer).unwrap();
+        self.pacing.step("Show balances").await;
 
         println!("\nHoldings after trade:");
         self.print_balances(&print_balances_request).await?;
         println!();
 
+        // 1 BTC buys 60000 USD, which in turn buys 300 RE
+        self.expect_balance("BTC", &unique_asset_id_btc, "Charlie", &charlie, 10_u32)
+            .await;
+        self.expect_balance("RE", &unique_asset_id_re, "Charlie", &charlie, 300_u32)
+            .await;
+        self.expect_supply_open_amount("USD => BTC", &supply_id_usd_btc, 940_000_u32)
+            .await;
+        self.expect_supply_open_amount("RE => USD", &supply_id_re_usd, 99_700_u32)
+            .await;
+
         Ok(())
     }
 
-    async fn print_balances(&self, req: &PrintBalancesRequest<'a>) -> Result<(), Box<dyn Error>> {
-        use prettytable::{Cell, Row, Table};
-
-        Service::TRACE.set(false);
-
-        let mut table = Table::new();
-
-        table.add_row(Row::new(vec![
-            Cell::new(&format!("{:<15}", "")),
-            Cell::new(&format!("Alice{:<15}", "")),
-            Cell::new(&format!("Bob{:<15}", "")),
-            Cell::new(&format!("Charlie{:<15}", "")),
-            Cell::new(&format!("exchange{:<15}", "")),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Real estate"),
-            Cell::new(
-                &self
-                    .service
-                    .int_get_balance(req.unique_asset_id_re, &req.alice.principal)
-                    .await?
-                    .to_string(),
-            ),
-            Cell::new(
-                &self
-                    .service
-                    .int_get_balance(req.unique_asset_id_re, &req.bob.principal)
-                    .await?
-                    .to_string(),
-            ),
-            Cell::new(
-                &self
-                    .service
-                    .int_get_balance(req.unique_asset_id_re, &req.charlie.principal)
-                    .await?
-                    .to_string(),
-            ),
-            Cell::new(
-                &self
-                    .service
-                    .int_get_balance(req.unique_asset_id_re, &req.exchange.principal)
-                    .await?
-                    .to_string(),
-            ),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("USD"),
-            Cell::new(
-                &self
-                    .service
-                    .int_get_balance(req.unique_asset_id_usd, &req.alice.principal)
-                    .await?
-                    .to_string(),
-            ),
-            Cell::new(
-                &self
-                    .service
-                    .int_get_balance(req.unique_asset_id_usd, &req.bob.principal)
-                    .await?
-                    .to_string(),
-            ),
-            Cell::new(
-                &self
-                    .service
-                    .int_get_balance(req.unique_asset_id_usd, &req.charlie.principal)
-                    .await?
-                    .to_string(),
-            ),
-            Cell::new(
-                &self
-                    .service
-                    .int_get_balance(req.unique_asset_id_usd, &req.exchange.principal)
-                    .await?
-                    .to_string(),
-            ),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("BTC"),
-            Cell::new(
-                &self
-                    .service
-                    .int_get_balance(req.unique_asset_id_btc, &req.alice.principal)
-                    .await?
-                    .to_string(),
-            ),
-            Cell::new(
-                &self
-                    .service
-                    .int_get_balance(req.unique_asset_id_btc, &req.bob.principal)
-                    .await?
-                    .to_string(),
-            ),
-            Cell::new(
-                &self
-                    .service
-                    .int_get_balance(req.unique_asset_id_btc, &req.charlie.principal)
-                    .await?
-                    .to_string(),
-            ),
-            Cell::new(
-                &self
-                    .service
-                    .int_get_balance(req.unique_asset_id_btc, &req.exchange.principal)
-                    .await?
-                    .to_string(),
-            ),
-        ]));
+    async fn expect_balance(
+        &self,
+        asset_name: &str,
+        unique_asset_id: &UniqueAssetId,
+        holder_name: &str,
+        holder: &User,
+        expected: impl Into<U256>,
+    ) {
+        let expected = expected.into();
+        let description = format!(
+            "{} holds {} {}",
+            holder_name,
+            self.registry.format_amount(unique_asset_id, &expected),
+            asset_name
+        );
 
-        table.printstd();
+        let outcome = match self
+            .service
+            .int_get_balance(unique_asset_id, &holder.principal)
+            .await
+        {
+            Ok(balance) if balance == expected => Ok(()),
+            Ok(balance) => Err(format!(
+                "actual balance is {}",
+                self.registry.format_amount(unique_asset_id, &balance)
+            )),
+            Err(e) => Err(format!("balance query failed: {e}")),
+        };
+        self.assertions.record(description, outcome);
+    }
+
+    async fn expect_supply_open_amount(
+        &self,
+        supply_name: &str,
+        supply_id: &SupplyId,
+        expected: impl Into<U256>,
+    ) {
+        let expected = expected.into();
+        let description = format!(
+            "supply {} ({}) has open amount {}",
+            supply_name, supply_id, expected
+        );
 
-        Service::TRACE.set(true);
+        let outcome = match self.service.int_get_supply(supply_id).await {
+            Ok(Some(supply)) if supply.open_amount == expected => Ok(()),
+            Ok(Some(supply)) => Err(format!("actual open amount is {}", supply.open_amount)),
+            Ok(None) => Err("supply not found".to_string()),
+            Err(e) => Err(format!("supply query failed: {e}")),
+        };
+        self.assertions.record(description, outcome);
+    }
+
+    async fn expect_event_count(&self, stream: EventStream, since: u64, expected_new: u64) {
+        let description = format!("{} new {} events", expected_new, stream);
+
+        let outcome = match self.service.event_count(&stream).await {
+            Ok(count) if count.saturating_sub(since) == expected_new => Ok(()),
+            Ok(count) => Err(format!("{} new events", count.saturating_sub(since))),
+            Err(e) => Err(format!("event count query failed: {e}")),
+        };
+        self.assertions.record(description, outcome);
+    }
+
+    /// Passes if the canister rejects or traps on `operation`.
+    async fn expect_failure<T>(
+        &self,
+        description: &str,
+        operation: impl Future<Output = Result<T, Box<dyn Error>>>,
+    ) {
+        let outcome = match operation.await {
+            Ok(_) => Err("operation succeeded".to_string()),
+            Err(e) => match e.downcast_ref::<AgentError>() {
+                // only a refusal by the canister itself counts, not a replica or transport error
+                Some(
+                    AgentError::CertifiedReject(reject) | AgentError::UncertifiedReject(reject),
+                ) if matches!(
+                    reject.reject_code,
+                    RejectCode::CanisterReject | RejectCode::CanisterError
+                ) =>
+                {
+                    Ok(())
+                }
+                _ => Err(format!("failed for another reason: {}", e)),
+            },
+        };
+        self.assertions.record(description.to_string(), outcome);
+    }
+
+    async fn print_balances(&self, req: &PrintBalancesRequest<'a>) -> Result<(), Box<dyn Error>> {
+        let holders = [
+            ("Alice", req.alice),
+            ("Bob", req.bob),
+            ("Charlie", req.charlie),
+            ("exchange", req.exchange),
+        ]
+        .into_iter()
+        .map(|(label, user)| ReportHolder {
+            label: label.to_string(),
+            principal: user.principal,
+        })
+        .collect();
+        let assets = [
+            req.unique_asset_id_re,
+            req.unique_asset_id_usd,
+            req.unique_asset_id_btc,
+        ]
+        .into_iter()
+        .map(|unique_asset_id| ReportAsset {
+            label: self.registry.label(unique_asset_id),
+            unique_asset_id: *unique_asset_id,
+            decimals: self.registry.decimals(unique_asset_id),
+            bitwise: self.registry.is_bitwise(unique_asset_id),
+        })
+        .collect();
+
+        let report =
+            BalanceReport::fetch(self.service, holders, assets, DEFAULT_MAX_IN_FLIGHT).await?;
+        print!("{}", report.render(ReportFormat::Table)?);
 
         Ok(())
     }
 
     fn calculate_sha_256(input: &str) -> Result<Nat, Box<dyn Error>> {
-        let mut sha256 = Sha256::new();
-        sha256.update(input);
-        let hash_bytes: [u8; 32] = sha256.finalize().into();
-        let hash_nat = Nat(BigUint::from_bytes_be(&hash_bytes));
-        Ok(hash_nat)
+        Ok(document::sha_256_nat(input.as_bytes()))
+    }
+
+    /// Request for a new asset with a random asset id that is not in use yet.
+    async fn new_asset_request(&self) -> Result<CreateAssetRequest, Box<dyn Error>> {
+        let asset_id = asset_creation::unused_asset_id(
+            self.service,
+            self.registry,
+            &self.clmp_contract_id,
+            self.ledger_id,
+            || asset_creation::random_asset_id(&mut *self.session.rng()),
+        )
+        .await?;
+        Ok(CreateAssetRequest::new(asset_id))
     }
 
     async fn create_asset(
         &self,
+        symbol: &str,
         req: &CreateAssetRequest,
     ) -> Result<UniqueAssetId, Box<dyn Error>> {
         self.service
@@ -368,25 +500,55 @@ impl<'a> Scenarios<'a> {
             )
             .await?;
 
-        let mut unique_asset_id_bytes: Vec<u8> = Vec::new();
-        let asset_id_bytes = req.asset_id.0.to_bytes_be();
-        for i in 0..asset_id_bytes.len() {
-            unique_asset_id_bytes.push(asset_id_bytes[i]);
-        }
-        unique_asset_id_bytes.extend_from_slice(&CLMP_LEDGER_ID.to_be_bytes());
-
-        let unique_asset_id = Nat(BigUint::from_bytes_be(&unique_asset_id_bytes));
+        let unique_asset_id = self
+            .registry
+            .register(symbol, 0, &req.asset_id, self.ledger_id)?;
+        self.registry.save()?;
 
         Ok(unique_asset_id)
     }
 
+    fn amount(&self, unique_asset_id: &UniqueAssetId, text: &str) -> Result<U256, Box<dyn Error>> {
+        self.registry.parse_amount(unique_asset_id, text)
+    }
+
+    fn describe_rate(
+        &self,
+        rate: &ExchangeRate,
+        offered: &UniqueAssetId,
+        desired: &UniqueAssetId,
+    ) -> String {
+        rate.describe(
+            (
+                &self.registry.label(offered),
+                self.registry.decimals(offered),
+            ),
+            (
+                &self.registry.label(desired),
+                self.registry.decimals(desired),
+            ),
+        )
+    }
+
     async fn run_warp(
         &self,
+        input_asset: &UniqueAssetId,
         supplies: Vec<SupplyId>,
         warp_amount: U256,
     ) -> Result<(), Box<dyn Error>> {
         let warp_target_address: Option<Principal> = None;
 
+        match FeeSchedule::load(self.service, DEFAULT_MAX_IN_FLIGHT).await {
+            Ok(schedule) => println!(
+                "Warp through {} supplies:\n{}",
+                supplies.len(),
+                schedule
+                    .preview(input_asset, &warp_amount)
+                    .render(self.registry)?
+            ),
+            Err(e) => warn!(error = %e, "could not read the fees of the warp"),
+        }
+
         let warp_req = RunWarpRequest {
             input_amount: warp_amount,
             target_address: warp_target_address,
@@ -398,32 +560,10 @@ impl<'a> Scenarios<'a> {
     }
 }
 
-struct CreateAssetRequest {
-    asset_id: AssetId,
-    asset_hash: Nat,
-    asset_bitwise: bool,
-    asset_controller: Option<ContractId>,
-}
-
-impl CreateAssetRequest {
-    pub fn default() -> Self {
-        let mut rng = rand::thread_rng();
-        let asset_id_bytes: [u8; 10] = rng.gen();
-        let asset_id = Nat(BigUint::from_bytes_be(&asset_id_bytes));
-
-        Self {
-            asset_id,
-            asset_hash: Nat::from(0x42 as u128),
-            asset_bitwise: false,
-            asset_controller: None,
-        }
-    }
-}
-
 struct PrintBalancesRequest<'a> {
-    unique_asset_id_re: &'a AssetId,
-    unique_asset_id_usd: &'a AssetId,
-    unique_asset_id_btc: &'a AssetId,
+    unique_asset_id_re: &'a UniqueAssetId,
+    unique_asset_id_usd: &'a UniqueAssetId,
+    unique_asset_id_btc: &'a UniqueAssetId,
     alice: &'a User,
     bob: &'a User,
     charlie: &'a User,
@@ -0,0 +1,50 @@
+use crate::cassette::CassetteHeader;
+use chrono::{DateTime, Utc};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    cell::{RefCell, RefMut},
+    error::Error,
+};
+
+/// Randomness and wall-clock time of a scenario run. Both are derived from a seed and a start
+/// time, so a run replayed from a cassette sends exactly the arguments of the recorded one.
+pub struct Session {
+    seed: u64,
+    started_at: DateTime<Utc>,
+    rng: RefCell<StdRng>,
+}
+
+impl Session {
+    pub fn new(seed: u64, started_at: DateTime<Utc>) -> Self {
+        Self {
+            seed,
+            started_at,
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    pub fn random() -> Self {
+        Self::new(rand::thread_rng().gen(), Utc::now())
+    }
+
+    pub fn from_header(header: &CassetteHeader) -> Result<Self, Box<dyn Error>> {
+        let started_at = DateTime::parse_from_rfc3339(&header.clock)?.with_timezone(&Utc);
+        Ok(Self::new(header.seed, started_at))
+    }
+
+    pub fn header(&self) -> CassetteHeader {
+        CassetteHeader {
+            seed: self.seed,
+            clock: self.started_at.to_rfc3339(),
+        }
+    }
+
+    pub fn rng(&self) -> RefMut<'_, StdRng> {
+        self.rng.borrow_mut()
+    }
+
+    /// Time the session started at; scenario timestamps are computed relative to it.
+    pub fn now(&self) -> DateTime<Utc> {
+        self.started_at
+    }
+}
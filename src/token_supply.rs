@@ -0,0 +1,348 @@
+use crate::{
+    events::{self, Event, EventStream},
+    Service, UniqueAssetId, U256,
+};
+use std::error::Error;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SupplyChange {
+    Issued,
+    Destroyed,
+}
+
+impl SupplyChange {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SupplyChange::Issued => "issued",
+            SupplyChange::Destroyed => "destroyed",
+        }
+    }
+}
+
+/// A `TokensCreated` or `TokensDestroyed` event of one asset.
+pub struct SupplyEvent {
+    pub change: SupplyChange,
+    pub event_ix: u64,
+    pub previous_amount: U256,
+    pub current_amount: U256,
+    /// Set if the event does not continue from the supply left by the previous event
+    pub gap: bool,
+}
+
+impl SupplyEvent {
+    /// Amount issued or destroyed by the event.
+    pub fn amount(&self) -> Result<U256, Box<dyn Error>> {
+        let amount = match self.change {
+            SupplyChange::Issued => self.current_amount.checked_sub(&self.previous_amount)?,
+            SupplyChange::Destroyed => self.previous_amount.checked_sub(&self.current_amount)?,
+        };
+        Ok(amount)
+    }
+}
+
+/// Issued, destroyed and outstanding tokens of an asset, with the events they are computed from.
+pub struct TokenSupply {
+    pub unique_asset_id: UniqueAssetId,
+    pub issued: U256,
+    pub destroyed: U256,
+    /// Live figure from `int_get_tokens`
+    pub outstanding: U256,
+    pub timeline: Vec<SupplyEvent>,
+    pub problems: Vec<String>,
+}
+
+/// Reads all `TokensCreated` and `TokensDestroyed` events once and computes the supply of every
+/// asset in `assets`.
+pub async fn fetch(
+    service: &Service,
+    assets: &[UniqueAssetId],
+    max_in_flight: usize,
+) -> Result<Vec<TokenSupply>, Box<dyn Error>> {
+    let created = events::fetch_events(service, EventStream::TokensCreated, max_in_flight).await?;
+    let destroyed =
+        events::fetch_events(service, EventStream::TokensDestroyed, max_in_flight).await?;
+
+    let mut supplies = Vec::new();
+    for unique_asset_id in assets {
+        let (asset_id, ledger_id) = unique_asset_id.decompose();
+        let contract_id = service
+            .int_get_ledger_contract_id(&ledger_id)
+            .await?
+            .ok_or("ledger contract not found")?;
+        let outstanding = service.int_get_tokens(&contract_id, &asset_id).await?;
+
+        let of_asset = |events: &[(u64, Event)], change| {
+            events
+                .iter()
+                .filter_map(|(event_ix, event)| {
+                    let (event_asset, previous_amount, current_amount) = match event {
+                        Event::TokensCreated(e) => {
+                            (e.unique_asset_id, &e.previous_amount, &e.current_amount)
+                        }
+                        Event::TokensDestroyed(e) => {
+                            (e.unique_asset_id, &e.previous_amount, &e.current_amount)
+                        }
+                        _ => return None,
+                    };
+                    (event_asset == *unique_asset_id).then(|| SupplyEvent {
+                        change,
+                        event_ix: *event_ix,
+                        previous_amount: previous_amount.clone(),
+                        current_amount: current_amount.clone(),
+                        gap: false,
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        let timeline = merge(
+            of_asset(&created, SupplyChange::Issued),
+            of_asset(&destroyed, SupplyChange::Destroyed),
+        );
+        supplies.push(summarize(*unique_asset_id, outstanding, timeline)?);
+    }
+
+    Ok(supplies)
+}
+
+/// Interleaves the issue and destroy events of an asset into one timeline. The two streams are
+/// ordered on their own only, so at every step the event continuing from the running supply is
+/// taken next; if neither does, the issue event is taken and marked as a gap.
+fn merge(issued: Vec<SupplyEvent>, destroyed: Vec<SupplyEvent>) -> Vec<SupplyEvent> {
+    let mut issued = issued.into_iter().peekable();
+    let mut destroyed = destroyed.into_iter().peekable();
+    let mut running = U256::zero();
+    let mut timeline = Vec::new();
+
+    loop {
+        let next = match (issued.peek(), destroyed.peek()) {
+            (Some(issue), Some(destroy))
+                if issue.previous_amount != running && destroy.previous_amount == running =>
+            {
+                destroyed.next()
+            }
+            (None, Some(_)) => destroyed.next(),
+            _ => issued.next(),
+        };
+        let Some(mut event) = next else {
+            break;
+        };
+
+        event.gap = event.previous_amount != running;
+        running = event.current_amount.clone();
+        timeline.push(event);
+    }
+
+    timeline
+}
+
+fn summarize(
+    unique_asset_id: UniqueAssetId,
+    outstanding: U256,
+    timeline: Vec<SupplyEvent>,
+) -> Result<TokenSupply, Box<dyn Error>> {
+    let mut issued = U256::zero();
+    let mut destroyed = U256::zero();
+    let mut problems = Vec::new();
+
+    for event in &timeline {
+        let amount = match event.amount() {
+            Ok(amount) => amount,
+            Err(_) => {
+                problems.push(format!(
+                    "{} event {} goes from {} to {}",
+                    event.change.as_str(),
+                    event.event_ix,
+                    event.previous_amount,
+                    event.current_amount
+                ));
+                continue;
+            }
+        };
+        match event.change {
+            SupplyChange::Issued => issued = issued.checked_add(&amount)?,
+            SupplyChange::Destroyed => destroyed = destroyed.checked_add(&amount)?,
+        }
+        if event.gap {
+            problems.push(format!(
+                "{} event {} starts from {}, not from the supply left by the previous event",
+                event.change.as_str(),
+                event.event_ix,
+                event.previous_amount
+            ));
+        }
+    }
+
+    match issued.checked_sub(&destroyed) {
+        Ok(expected) if expected == outstanding => {}
+        Ok(expected) => problems.push(format!(
+            "events account for {} tokens but the ledger reports {}",
+            expected, outstanding
+        )),
+        Err(_) => problems.push("more tokens destroyed than issued".to_string()),
+    }
+
+    Ok(TokenSupply {
+        unique_asset_id,
+        issued,
+        destroyed,
+        outstanding,
+        timeline,
+        problems,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Nat;
+
+    fn event(change: SupplyChange, event_ix: u64, previous: u32, current: u32) -> SupplyEvent {
+        SupplyEvent {
+            change,
+            event_ix,
+            previous_amount: U256::from(previous),
+            current_amount: U256::from(current),
+            gap: false,
+        }
+    }
+
+    fn issued(event_ix: u64, previous: u32, current: u32) -> SupplyEvent {
+        event(SupplyChange::Issued, event_ix, previous, current)
+    }
+
+    fn destroyed(event_ix: u64, previous: u32, current: u32) -> SupplyEvent {
+        event(SupplyChange::Destroyed, event_ix, previous, current)
+    }
+
+    fn steps(timeline: &[SupplyEvent]) -> Vec<(&'static str, u64, bool)> {
+        timeline
+            .iter()
+            .map(|e| (e.change.as_str(), e.event_ix, e.gap))
+            .collect()
+    }
+
+    fn unique_asset_id() -> UniqueAssetId {
+        UniqueAssetId::compose(&Nat::from(7u8), 1).unwrap()
+    }
+
+    #[test]
+    fn merge_follows_the_running_supply() {
+        // issue 100, destroy 30, issue 50, destroy 20
+        let timeline = merge(
+            vec![issued(0, 0, 100), issued(1, 70, 120)],
+            vec![destroyed(0, 100, 70), destroyed(1, 120, 100)],
+        );
+        assert_eq!(
+            steps(&timeline),
+            vec![
+                ("issued", 0, false),
+                ("destroyed", 0, false),
+                ("issued", 1, false),
+                ("destroyed", 1, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_takes_remaining_events_of_one_stream() {
+        let timeline = merge(vec![issued(0, 0, 10), issued(1, 10, 25)], Vec::new());
+        assert_eq!(
+            steps(&timeline),
+            vec![("issued", 0, false), ("issued", 1, false)]
+        );
+        let timeline = merge(
+            vec![issued(0, 0, 10)],
+            vec![destroyed(0, 10, 4), destroyed(1, 4, 0)],
+        );
+        assert_eq!(
+            steps(&timeline),
+            vec![
+                ("issued", 0, false),
+                ("destroyed", 0, false),
+                ("destroyed", 1, false)
+            ]
+        );
+        assert!(merge(Vec::new(), Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn merge_marks_gaps() {
+        // neither stream continues from 100, so the issue event is taken as a gap
+        let timeline = merge(
+            vec![issued(0, 0, 100), issued(1, 90, 150)],
+            vec![destroyed(0, 80, 60)],
+        );
+        assert_eq!(
+            steps(&timeline),
+            vec![
+                ("issued", 0, false),
+                ("issued", 1, true),
+                ("destroyed", 0, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_marks_gaps_in_both_streams_at_the_same_step() {
+        // after issuing 100 neither the issue from 110 nor the destroy from 90 continues, and
+        // after taking the issue as a gap the destroy from 90 still does not
+        let timeline = merge(
+            vec![issued(0, 0, 100), issued(1, 110, 130)],
+            vec![destroyed(0, 90, 80), destroyed(1, 130, 120)],
+        );
+        assert_eq!(
+            steps(&timeline),
+            vec![
+                ("issued", 0, false),
+                ("issued", 1, true),
+                ("destroyed", 0, true),
+                ("destroyed", 1, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn summarize_totals_a_consistent_timeline() {
+        let timeline = merge(vec![issued(0, 0, 100)], vec![destroyed(0, 100, 40)]);
+        let supply = summarize(unique_asset_id(), U256::from(40u8), timeline).unwrap();
+        assert_eq!(supply.issued, U256::from(100u8));
+        assert_eq!(supply.destroyed, U256::from(60u8));
+        assert!(supply.problems.is_empty());
+    }
+
+    #[test]
+    fn summarize_reports_gaps_and_mismatches() {
+        let timeline = merge(vec![issued(0, 0, 100), issued(1, 120, 150)], Vec::new());
+        let supply = summarize(unique_asset_id(), U256::from(130u8), timeline).unwrap();
+        assert_eq!(supply.issued, U256::from(130u8));
+        assert_eq!(
+            supply.problems,
+            vec![
+                "issued event 1 starts from 120, not from the supply left by the previous event"
+                    .to_string()
+            ]
+        );
+
+        let supply = summarize(unique_asset_id(), U256::from(99u8), Vec::new()).unwrap();
+        assert_eq!(
+            supply.problems,
+            vec!["events account for 0 tokens but the ledger reports 99".to_string()]
+        );
+    }
+
+    #[test]
+    fn summarize_reports_inconsistent_events() {
+        // an issue event that lowers the supply cannot be counted
+        let supply = summarize(unique_asset_id(), U256::zero(), vec![issued(3, 50, 20)]).unwrap();
+        assert_eq!(
+            supply.problems,
+            vec!["issued event 3 goes from 50 to 20".to_string()]
+        );
+
+        let supply = summarize(unique_asset_id(), U256::zero(), vec![destroyed(0, 10, 0)]).unwrap();
+        assert_eq!(
+            supply.problems,
+            vec!["more tokens destroyed than issued".to_string()]
+        );
+    }
+}
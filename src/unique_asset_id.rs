@@ -0,0 +1,346 @@
+use crate::{AssetId, LedgerId};
+use candid::{
+    types::{Serializer, Type, TypeInner},
+    CandidType, Nat,
+};
+use data_encoding::BASE32_NOPAD;
+use num_bigint::BigUint;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+use std::{error::Error, fmt, str::FromStr};
+
+pub const ASSET_ID_SIZE: usize = 10;
+pub const LEDGER_ID_SIZE: usize = 2;
+pub const UNIQUE_ASSET_ID_SIZE: usize = ASSET_ID_SIZE + LEDGER_ID_SIZE;
+
+const TEXT_PREFIX: &str = "ua-";
+const CHECKSUM_SIZE: usize = 4;
+
+/// Globally unique id of an asset: the 10-byte asset id followed by the big-endian 2-byte id
+/// of the ledger the asset lives on. On the wire it is a Candid `nat`.
+///
+/// Textual forms accepted by `FromStr`:
+/// - decimal: `1234567890`
+/// - hex: `0x0000A1B2C3D4E5F601020001`
+/// - checksummed: `ua-xxxxx-xxxxx-xxxxx-xxxxx-xxxxx-x` (base32 of checksum and bytes)
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct UniqueAssetId([u8; UNIQUE_ASSET_ID_SIZE]);
+
+#[derive(Debug)]
+pub enum UniqueAssetIdError {
+    AssetIdTooLarge,
+    ValueTooLarge,
+    InvalidFormat(String),
+    InvalidChecksum,
+}
+
+impl fmt::Display for UniqueAssetIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UniqueAssetIdError::AssetIdTooLarge => {
+                write!(f, "asset id does not fit into {} bytes", ASSET_ID_SIZE)
+            }
+            UniqueAssetIdError::ValueTooLarge => {
+                write!(
+                    f,
+                    "unique asset id does not fit into {} bytes",
+                    UNIQUE_ASSET_ID_SIZE
+                )
+            }
+            UniqueAssetIdError::InvalidFormat(text) => {
+                write!(f, "invalid unique asset id: {}", text)
+            }
+            UniqueAssetIdError::InvalidChecksum => write!(f, "unique asset id checksum mismatch"),
+        }
+    }
+}
+
+impl Error for UniqueAssetIdError {}
+
+impl UniqueAssetId {
+    pub fn compose(asset_id: &AssetId, ledger_id: LedgerId) -> Result<Self, UniqueAssetIdError> {
+        let asset_id_bytes = asset_id.0.to_bytes_be();
+        let asset_id_bytes = strip_leading_zeros(&asset_id_bytes);
+        if asset_id_bytes.len() > ASSET_ID_SIZE {
+            return Err(UniqueAssetIdError::AssetIdTooLarge);
+        }
+
+        let mut bytes = [0u8; UNIQUE_ASSET_ID_SIZE];
+        bytes[ASSET_ID_SIZE - asset_id_bytes.len()..ASSET_ID_SIZE].copy_from_slice(asset_id_bytes);
+        bytes[ASSET_ID_SIZE..].copy_from_slice(&ledger_id.to_be_bytes());
+        Ok(Self(bytes))
+    }
+
+    pub fn decompose(&self) -> (AssetId, LedgerId) {
+        (self.asset_id(), self.ledger_id())
+    }
+
+    pub fn asset_id(&self) -> AssetId {
+        Nat(BigUint::from_bytes_be(&self.0[..ASSET_ID_SIZE]))
+    }
+
+    pub fn ledger_id(&self) -> LedgerId {
+        LedgerId::from_be_bytes([self.0[ASSET_ID_SIZE], self.0[ASSET_ID_SIZE + 1]])
+    }
+
+    pub fn as_bytes(&self) -> &[u8; UNIQUE_ASSET_ID_SIZE] {
+        &self.0
+    }
+
+    pub fn to_nat(self) -> Nat {
+        Nat(BigUint::from_bytes_be(&self.0))
+    }
+
+    pub fn to_decimal(self) -> String {
+        BigUint::from_bytes_be(&self.0).to_string()
+    }
+
+    pub fn to_hex(self) -> String {
+        format!("0x{}", hex::encode_upper(self.0))
+    }
+
+    pub fn to_text(self) -> String {
+        let mut data = Vec::with_capacity(CHECKSUM_SIZE + UNIQUE_ASSET_ID_SIZE);
+        data.extend_from_slice(&checksum(&self.0));
+        data.extend_from_slice(&self.0);
+
+        let encoded = BASE32_NOPAD.encode(&data).to_lowercase();
+        let groups: Vec<&str> = encoded
+            .as_bytes()
+            .chunks(5)
+            .map(|chunk| std::str::from_utf8(chunk).unwrap())
+            .collect();
+        format!("{}{}", TEXT_PREFIX, groups.join("-"))
+    }
+
+    fn from_text(text: &str) -> Result<Self, UniqueAssetIdError> {
+        let invalid = || UniqueAssetIdError::InvalidFormat(text.to_string());
+
+        let encoded = text[TEXT_PREFIX.len()..].replace('-', "").to_uppercase();
+        let data = BASE32_NOPAD
+            .decode(encoded.as_bytes())
+            .map_err(|_| invalid())?;
+        if data.len() != CHECKSUM_SIZE + UNIQUE_ASSET_ID_SIZE {
+            return Err(invalid());
+        }
+
+        let (expected_checksum, bytes) = data.split_at(CHECKSUM_SIZE);
+        if checksum(bytes) != expected_checksum {
+            return Err(UniqueAssetIdError::InvalidChecksum);
+        }
+        Ok(Self(bytes.try_into().unwrap()))
+    }
+}
+
+fn checksum(bytes: &[u8]) -> [u8; CHECKSUM_SIZE] {
+    let hash: [u8; 32] = Sha256::digest(bytes).into();
+    hash[..CHECKSUM_SIZE].try_into().unwrap()
+}
+
+fn strip_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let first_non_zero = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    &bytes[first_non_zero..]
+}
+
+impl TryFrom<&Nat> for UniqueAssetId {
+    type Error = UniqueAssetIdError;
+
+    fn try_from(value: &Nat) -> Result<Self, Self::Error> {
+        let value_bytes = value.0.to_bytes_be();
+        let value_bytes = strip_leading_zeros(&value_bytes);
+        if value_bytes.len() > UNIQUE_ASSET_ID_SIZE {
+            return Err(UniqueAssetIdError::ValueTooLarge);
+        }
+
+        let mut bytes = [0u8; UNIQUE_ASSET_ID_SIZE];
+        bytes[UNIQUE_ASSET_ID_SIZE - value_bytes.len()..].copy_from_slice(value_bytes);
+        Ok(Self(bytes))
+    }
+}
+
+impl From<UniqueAssetId> for Nat {
+    fn from(value: UniqueAssetId) -> Self {
+        value.to_nat()
+    }
+}
+
+impl FromStr for UniqueAssetId {
+    type Err = UniqueAssetIdError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || UniqueAssetIdError::InvalidFormat(text.to_string());
+
+        if text.starts_with(TEXT_PREFIX) {
+            return Self::from_text(text);
+        }
+
+        let value = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            Some(hex_digits) => {
+                BigUint::parse_bytes(hex_digits.as_bytes(), 16).ok_or_else(invalid)?
+            }
+            None => {
+                BigUint::parse_bytes(text.replace('_', "").as_bytes(), 10).ok_or_else(invalid)?
+            }
+        };
+        Self::try_from(&Nat(value))
+    }
+}
+
+impl fmt::Display for UniqueAssetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl fmt::Debug for UniqueAssetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "UniqueAssetId({})", self.to_hex())
+    }
+}
+
+impl CandidType for UniqueAssetId {
+    fn _ty() -> Type {
+        TypeInner::Nat.into()
+    }
+
+    fn idl_serialize<S>(&self, serializer: S) -> Result<(), S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_nat(&self.to_nat())
+    }
+}
+
+impl Serialize for UniqueAssetId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for UniqueAssetId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            let text = String::deserialize(deserializer)?;
+            text.parse().map_err(de::Error::custom)
+        } else {
+            // the Candid deserializer is not human readable and yields a `nat`
+            let value = Nat::deserialize(deserializer)?;
+            Self::try_from(&value).map_err(de::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::{Decode, Encode};
+
+    fn sample() -> UniqueAssetId {
+        let asset_id = Nat(BigUint::parse_bytes(b"A1B2C3D4E5F60102", 16).unwrap());
+        UniqueAssetId::compose(&asset_id, 1).unwrap()
+    }
+
+    #[test]
+    fn compose_and_decompose_round_trip() {
+        let unique_asset_id = sample();
+        assert_eq!(unique_asset_id.to_hex(), "0x0000A1B2C3D4E5F601020001");
+
+        let (asset_id, ledger_id) = unique_asset_id.decompose();
+        assert_eq!(ledger_id, 1);
+        assert_eq!(
+            UniqueAssetId::compose(&asset_id, ledger_id).unwrap(),
+            unique_asset_id
+        );
+    }
+
+    #[test]
+    fn compose_rejects_asset_ids_above_ten_bytes() {
+        let asset_id = Nat(BigUint::from(1u8) << (8 * ASSET_ID_SIZE));
+        assert!(matches!(
+            UniqueAssetId::compose(&asset_id, 1),
+            Err(UniqueAssetIdError::AssetIdTooLarge)
+        ));
+    }
+
+    #[test]
+    fn every_textual_form_parses_back() {
+        let unique_asset_id = sample();
+        for text in [
+            unique_asset_id.to_decimal(),
+            unique_asset_id.to_hex(),
+            unique_asset_id.to_hex().to_lowercase(),
+            unique_asset_id.to_text(),
+            unique_asset_id
+                .to_text()
+                .to_uppercase()
+                .replacen("UA-", "ua-", 1),
+        ] {
+            assert_eq!(
+                text.parse::<UniqueAssetId>().unwrap(),
+                unique_asset_id,
+                "{text}"
+            );
+        }
+        assert_eq!(
+            "1_000".parse::<UniqueAssetId>().unwrap().to_decimal(),
+            "1000"
+        );
+    }
+
+    #[test]
+    fn checksummed_text_detects_typos() {
+        let text = sample().to_text();
+        assert!(text.starts_with("ua-"));
+
+        // change one character of the encoded bytes
+        let position = text.len() / 2;
+        let original = text.as_bytes()[position] as char;
+        let replacement = if original == 'a' { 'b' } else { 'a' };
+        let mut typo = text.clone();
+        typo.replace_range(position..=position, &replacement.to_string());
+        assert!(matches!(
+            typo.parse::<UniqueAssetId>(),
+            Err(UniqueAssetIdError::InvalidChecksum)
+        ));
+    }
+
+    #[test]
+    fn malformed_text_is_rejected() {
+        for text in ["", "ua-", "ua-abc", "ua-!!!!!", "0x", "0xZZ", "12a", "-1"] {
+            assert!(
+                matches!(
+                    text.parse::<UniqueAssetId>(),
+                    Err(UniqueAssetIdError::InvalidFormat(_))
+                ),
+                "{text} should be rejected"
+            );
+        }
+        let too_large = format!("0x01{}", "00".repeat(UNIQUE_ASSET_ID_SIZE));
+        assert!(matches!(
+            too_large.parse::<UniqueAssetId>(),
+            Err(UniqueAssetIdError::ValueTooLarge)
+        ));
+    }
+
+    #[test]
+    fn candid_and_json_round_trip() {
+        let unique_asset_id = sample();
+
+        let bytes = Encode!(&unique_asset_id).unwrap();
+        assert_eq!(bytes, Encode!(&unique_asset_id.to_nat()).unwrap());
+        assert_eq!(Decode!(&bytes, UniqueAssetId).unwrap(), unique_asset_id);
+
+        let json = serde_json::to_string(&unique_asset_id).unwrap();
+        assert_eq!(json, "\"0x0000A1B2C3D4E5F601020001\"");
+        assert_eq!(
+            serde_json::from_str::<UniqueAssetId>(&json).unwrap(),
+            unique_asset_id
+        );
+    }
+}
@@ -0,0 +1,135 @@
+//! Replays checked-in cassettes through the binary, without network access.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+const ASSET: &str = "0x0000A1B2C3D4E5F601020001";
+
+fn cassette(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/cassettes")
+        .join(name)
+}
+
+/// Runs `balances` against a cassette from an empty directory, so that no local registry,
+/// manifest or audit log can take part.
+fn replay_balances(cassette: &Path, name: &str) -> Output {
+    let dir = std::env::temp_dir().join(format!("demo-replay-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_demo"))
+        .current_dir(&dir)
+        .args(["--replay".as_ref(), cassette.as_os_str()])
+        .args(["balances", "--holder", "2vxsx-fae", "--holder", "aaaaa-aa"])
+        .args(["--asset", ASSET, "--format", "json"])
+        .output()
+        .unwrap();
+
+    assert!(
+        fs::read_dir(&dir).unwrap().next().is_none(),
+        "replay wrote local files"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+    output
+}
+
+/// Copy of a checked-in cassette with `edit` applied to its lines.
+fn edited_cassette(name: &str, edit: impl FnOnce(&mut Vec<String>)) -> PathBuf {
+    let mut lines = fs::read_to_string(cassette("balances.jsonl"))
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    edit(&mut lines);
+    let file_path = std::env::temp_dir().join(format!(
+        "demo-cassette-{}-{}.jsonl",
+        name,
+        std::process::id()
+    ));
+    fs::write(&file_path, lines.join("\n") + "\n").unwrap();
+    file_path
+}
+
+#[test]
+fn replays_balances_in_issue_order() {
+    let output = replay_balances(&cassette("balances.jsonl"), "balances");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // the calls completed in reverse order, their slots put them back
+    let anonymous = stdout.find(r#""balance": "1500""#).expect(&stdout);
+    let management = stdout.find(r#""balance": "42""#).expect(&stdout);
+    assert!(anonymous < management);
+    assert!(stdout.contains("no replica is contacted"));
+}
+
+#[test]
+fn mismatching_call_aborts_the_replay() {
+    // the first recorded call now names another asset (the first byte of its LEB128 id)
+    let file_path = edited_cassette("mismatch", |lines| {
+        let line = lines
+            .iter_mut()
+            .find(|l| l.contains(r#""slot":0"#))
+            .unwrap();
+        *line = line.replace("4449444c00027d6881", "4449444c00027d6882");
+    });
+    let output = replay_balances(&file_path, "mismatch");
+    fs::remove_file(&file_path).unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("call #0 does not match"), "{}", stderr);
+}
+
+#[test]
+fn unplayed_calls_fail_the_replay() {
+    let file_path = edited_cassette("extra", |lines| {
+        let extra = lines[1].replace(r#""slot":1"#, r#""slot":2"#);
+        lines.push(extra);
+    });
+    let output = replay_balances(&file_path, "extra");
+    fs::remove_file(&file_path).unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("only 2 of 3 recorded calls were made"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn recorded_rejects_are_replayed() {
+    // the first call was rejected by the canister instead of answering 1500
+    let file_path = edited_cassette("reject", |lines| {
+        let line = lines
+            .iter_mut()
+            .find(|l| l.contains(r#""slot":0"#))
+            .unwrap();
+        *line = line
+            .replace(r#""response":"4449444c00017ddc0b""#, r#""response":null"#)
+            .replace(
+                r#""error":null}"#,
+                r#""error":"rejected","reject":{"certified":false,"reject_code":5,"reject_message":"unknown asset","error_code":null}}"#,
+            );
+    });
+    let output = replay_balances(&file_path, "reject");
+    fs::remove_file(&file_path).unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("reject code CanisterError, reject message unknown asset"),
+        "{}",
+        stderr
+    );
+}